@@ -0,0 +1,55 @@
+// the tree walker against the bytecode VM, evaluating one formula over a grid as a plot
+// does. run with `cargo bench --bench vm`
+
+use rust_tutorial::calculator_ref::number::Number;
+use rust_tutorial::{Ast, Code, Context, Interpreter, Value};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const FORMULA: &str = "sin(x) * cos(y) + sqrt(x^2 + y^2) / (1 + abs(x - y)) - 2 * x * y";
+const SIZE: usize = 400;
+
+// the sum over the grid, which both have to agree on, and how long it took
+fn grid(x: &Cell<f64>, y: &Cell<f64>, mut eval: impl FnMut() -> Value) -> (f64, Duration) {
+  let start = Instant::now();
+  let mut sum = 0.0;
+  for i in 0..SIZE {
+    for j in 0..SIZE {
+      x.set(i as f64 / 40.0 - 5.0);
+      y.set(j as f64 / 40.0 - 5.0);
+      sum += eval().to_f64().unwrap();
+    }
+  }
+  (sum, start.elapsed())
+}
+
+fn main() {
+  let x = Rc::new(Cell::new(0.0));
+  let y = Rc::new(Cell::new(0.0));
+  let mut context = Context::new();
+  let (read_x, read_y) = (Rc::clone(&x), Rc::clone(&y));
+  context.define_variable("x", move || Value::Float(read_x.get()));
+  context.define_variable("y", move || Value::Float(read_y.get()));
+  let mut interpreter = Interpreter::with_context(context);
+
+  let ast: Ast = FORMULA.parse().unwrap();
+  let code = Code::compile(&ast);
+  let (tree_sum, tree) = grid(&x, &y, || interpreter.eval(&ast).unwrap());
+  let (vm_sum, vm) = grid(&x, &y, || interpreter.run(&code).unwrap());
+  assert_eq!(tree_sum, vm_sum);
+
+  let points = (SIZE * SIZE) as f64;
+  println!("{} over {} points", FORMULA, SIZE * SIZE);
+  println!(
+    "tree walker: {:>10.2?} ({:.0} ns/point)",
+    tree,
+    tree.as_nanos() as f64 / points
+  );
+  println!(
+    "bytecode VM: {:>10.2?} ({:.0} ns/point, {:.2}x)",
+    vm,
+    vm.as_nanos() as f64 / points,
+    tree.as_secs_f64() / vm.as_secs_f64()
+  );
+}
@@ -1,14 +1,25 @@
 // ref: https://github.com/ghmagazine/rustbook/tree/master/ch09/parser
 
+// a span of the input in byte offsets, always at character boundaries
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct Loc(usize, usize);
+pub struct Loc(usize, usize);
 impl Loc {
+  pub fn new(start: usize, end: usize) -> Loc {
+    Loc(start, end)
+  }
+  pub fn start(&self) -> usize {
+    self.0
+  }
+  pub fn end(&self) -> usize {
+    self.1
+  }
   fn merge(&self, other: &Loc) -> Loc {
     use std::cmp::{max, min};
     Loc(min(self.0, other.0), max(self.1, other.1))
   }
 }
 
+use crate::i18n::{tr, tr_with};
 use std::fmt;
 impl fmt::Display for Loc {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -22,24 +33,55 @@ pub struct Annot<T> {
   loc: Loc,
 }
 impl<T> Annot<T> {
-  fn new(value: T, loc: Loc) -> Annot<T> {
+  pub fn new(value: T, loc: Loc) -> Annot<T> {
     Self { value, loc }
   }
+  pub fn value(&self) -> &T {
+    &self.value
+  }
+  pub fn loc(&self) -> &Loc {
+    &self.loc
+  }
 }
 
+pub mod bignum;
+pub mod builtins;
+pub mod codes;
+pub mod complex;
+pub mod context;
+pub mod derivative;
+pub mod diagnostic;
+pub mod json;
+pub mod limits;
+pub mod number;
+pub mod operator;
+pub mod optimize;
+pub mod rational;
+pub mod solve;
+pub mod trace;
+pub mod vm;
+
 use ast::Ast;
 use ast::ParseError;
 use lexer::LexError;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Error {
   Lexer(LexError),
   Parser(ParseError),
+  Interpreter(InterpreterError),
+  // every error in an input with more than one, the lexer's first
+  Multiple(Vec<Error>),
 }
 
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "parser error")
+    match self {
+      Error::Lexer(e) => write!(f, "{}", tr_with("error.lexer", &[&e.value.code()])),
+      Error::Parser(e) => write!(f, "{}", tr_with("error.parser", &[&e.code()])),
+      Error::Interpreter(e) => write!(f, "{}", tr_with("error.interpreter", &[&e.value.code()])),
+      Error::Multiple(errors) => write!(f, "{}", tr_with("error.multiple", &[&errors.len()])),
+    }
   }
 }
 
@@ -55,6 +97,25 @@ impl From<ParseError> for Error {
   }
 }
 
+impl From<InterpreterError> for Error {
+  fn from(e: InterpreterError) -> Self {
+    Error::Interpreter(e)
+  }
+}
+
+// parses and evaluates `input` with a fresh interpreter of the machine mode
+pub fn evaluate(input: &str) -> Result<interpreter::Value, Error> {
+  evaluate_limited(input, &Limits::default())
+}
+
+// evaluates as `evaluate` does, parsing and evaluating within `limits`
+pub fn evaluate_limited(input: &str, limits: &Limits) -> Result<interpreter::Value, Error> {
+  let ast = Ast::from_str_limited(input, limits)?;
+  let mut interpreter = interpreter::Interpreter::new();
+  interpreter.set_limits(*limits);
+  Ok(interpreter.eval(&ast)?)
+}
+
 pub use std::error::Error as StdError;
 impl StdError for LexError {}
 impl StdError for ParseError {}
@@ -64,6 +125,8 @@ impl StdError for Error {
     match self {
       Lexer(lex) => Some(lex),
       Parser(parse) => Some(parse),
+      Interpreter(e) => Some(e),
+      Multiple(errors) => errors.first().and_then(|e| e.source()),
     }
   }
 }
@@ -74,47 +137,220 @@ impl StdError for InterpreterError {
     use self::InterpreterErrorKind::*;
     match self.value {
       DivisionByZero => "the right hand expression of the division evaluates to zero",
+      Overflow => "the result does not fit in a 64-bit signed integer",
+      Unsupported { .. } => "the selected number type does not implement the operation",
+      Domain(_) => "an operand is outside the domain of the operation",
+      UndefinedVariable(_) => "the variable is read before any value is assigned to it",
+      UnknownFunction(_) => "no built-in function has the name",
+      ArityMismatch { .. } => "the function is called with the wrong number of arguments",
+      SyntaxError => "a partially parsed expression is evaluated where it has an error",
+      ReadOnly(_) => "the host program defines the name, so it cannot be assigned to",
+      NotDifferentiable(_) => "the expression has no derivative by the rules there are",
+      NoSignChange { .. } => "the expression has the same sign at both ends of the interval",
+      NoConvergence(_) => "the search for a root did not settle on one",
+      LimitExceeded(_) => "the evaluation went past a limit the interpreter is set to",
+      NotVariable(_) => "the expression to solve for is not a variable",
     }
   }
 }
 
-fn print_annot(input: &str, loc: Loc) {
-  eprintln!("{}", input);
-  eprintln!("{}{}", " ".repeat(loc.0), "^".repeat(loc.1 - loc.0))
+use diagnostic::Diagnostic;
+use json::Json;
+use std::io::IsTerminal;
+
+// diagnostics are colored only when standard error, which they are written to, is a terminal
+fn show(diagnostic: Diagnostic, input: &str) {
+  eprint!(
+    "{}",
+    diagnostic.render(input, std::io::stderr().is_terminal())
+  );
 }
 
 impl Error {
-  pub fn show_diagnostic(&self, input: &str) {
+  // the errors one by one, as `Multiple` holds several
+  fn each(&self) -> Vec<&Error> {
+    match self {
+      Error::Multiple(errors) => errors.iter().flat_map(|e| e.each()).collect(),
+      e => vec![e],
+    }
+  }
+
+  // the variant name and the code of a single error
+  fn kind(&self) -> (&'static str, &'static str) {
+    match self {
+      Error::Lexer(e) => (e.value.kind(), e.value.code()),
+      Error::Parser(e) => (e.kind(), e.code()),
+      Error::Interpreter(e) => (e.value.kind(), e.value.code()),
+      Error::Multiple(_) => unreachable!("an error of several has no single kind"),
+    }
+  }
+
+  // where a single error is in `input`, and its message without the location
+  fn locate(&self, input: &str) -> (Loc, String) {
     use self::Error::*;
     use self::ParseError as P;
-    use lexer::Token;
 
-    let (e, loc): (&dyn StdError, Loc) = match self {
-      Lexer(e) => (e, e.loc.clone()),
+    match self {
+      Multiple(_) => unreachable!("an error of several has no single location"),
+      Lexer(e) => (e.loc.clone(), e.value.to_string()),
+      Interpreter(e) => (e.loc.clone(), e.to_string()),
       Parser(e) => {
         let loc = match e {
-          P::UnexpectedToken(Token { loc, .. })
-          | P::NotExpression(Token { loc, .. })
-          | P::NotOperator(Token { loc, .. })
-          | P::UnclosedOpenParen(Token { loc, .. }) => loc.clone(),
-          P::RedundantExpression(Token { loc, .. }) => Loc(loc.0, input.len()),
-          P::Eof => Loc(input.len(), input.len() + 1),
+          P::RedundantExpression(token) => Loc(token.loc.0, input.len()),
+          P::Eof => Loc(input.len(), input.len()),
+          e => e.token().unwrap().loc.clone(),
         };
-        (e, loc)
+        (loc, e.message())
       }
-    };
-    eprintln!("{}", e);
-    print_annot(input, loc);
+    }
+  }
+
+  pub fn diagnostics(&self, input: &str) -> Vec<Diagnostic> {
+    use self::Error::*;
+    use self::ParseError as P;
+    use lexer::LexErrorKind as L;
+
+    self
+      .each()
+      .into_iter()
+      .map(|e| {
+        let (loc, message) = e.locate(input);
+        let d = Diagnostic::error(message).code(e.kind().1);
+        match e {
+          Multiple(_) => unreachable!(),
+          // the tree is only for pointing into, so the default operators are good enough,
+          // and it is parsed to any depth, as the input evaluated may have been
+          Interpreter(e) => {
+            let limits = Limits {
+              depth: usize::MAX,
+              ..Limits::default()
+            };
+            e.diagnostic(&Ast::parse_limited(input, &OperatorTable::default(), &limits).0)
+          }
+          Lexer(e) => match e.value {
+            L::InvalidChar(_) => d.primary(loc, tr("label.not_token")),
+            L::NumberTooLarge => d
+              .primary(loc, tr("label.float_range"))
+              .note(tr("note.float_max")),
+            L::Eof => d.primary(loc, tr("label.input_ends")),
+          },
+          Parser(e) => match e {
+            P::UnexpectedToken(_) => d.primary(loc, tr("label.expected_comma")),
+            P::NotExpression(_) => d.primary(loc, tr("label.expected_start")),
+            P::NotOperator(_) => d.primary(loc, tr("label.expected_operator")),
+            P::UnclosedOpenParen(_) => d
+              .primary(loc, tr("label.unclosed_paren"))
+              .help(tr("help.close_paren")),
+            P::RedundantExpression(_) => d
+              .primary(loc, tr("label.expression_ended"))
+              .help(tr("help.join")),
+            P::NotAssignable(_) => d
+              .primary(loc, tr("label.not_assignable"))
+              .help(tr("help.assign_variable")),
+            P::TooDeep(..) => d
+              .primary(loc, tr("label.too_deep"))
+              .help(tr("help.split_expression")),
+            P::Eof => d.primary(loc, tr("label.expected_expression")),
+          },
+        }
+      })
+      .collect()
+  }
+
+  // one JSON object per error
+  pub fn to_json(&self, input: &str) -> Vec<Json> {
+    self
+      .each()
+      .into_iter()
+      .map(|e| match e {
+        Error::Interpreter(e) => e.to_json(input),
+        e => {
+          let (kind, code) = e.kind();
+          let (loc, message) = e.locate(input);
+          json::error(kind, code, message, &loc, input, e)
+        }
+      })
+      .collect()
+  }
+
+  pub fn show_diagnostic(&self, input: &str) {
+    for diagnostic in self.diagnostics(input) {
+      show(diagnostic, input);
+    }
   }
 }
 
 use interpreter::InterpreterError;
 impl InterpreterError {
-  pub fn show_diagnostic(&self, input: &str) {
-    // エラー情報を簡単に表示し
-    eprintln!("{}", self);
-    // エラー位置を指示する
-    print_annot(input, self.loc.clone());
+  // `expr` is the expression whose evaluation failed, to point into it
+  pub fn diagnostic(&self, expr: &Ast) -> Diagnostic {
+    use self::InterpreterErrorKind::*;
+    use ast::{AstKind, BinOpKind};
+    use limits::Limit;
+
+    let d = Diagnostic::error(self.to_string()).code(self.value.code());
+    let loc = self.loc.clone();
+    match self.value {
+      DivisionByZero => match expr.find(&loc).map(|e| &e.value) {
+        Some(AstKind::BinOp { op, r, .. }) if op.value == BinOpKind::Div => d
+          .primary(op.loc.clone(), tr("label.division_by_zero"))
+          .secondary(r.loc.clone(), tr("label.evaluates_to_zero")),
+        Some(AstKind::BinOp { op, l, .. }) if op.value == BinOpKind::Pow => d
+          .primary(op.loc.clone(), tr("label.negative_power_of_zero"))
+          .secondary(l.loc.clone(), tr("label.evaluates_to_zero")),
+        _ => d.primary(loc, tr("label.division_by_zero")),
+      },
+      Overflow => d
+        .primary(loc, tr("label.too_large"))
+        .help(tr("help.bignum")),
+      Unsupported { .. } => d
+        .primary(loc, tr("label.unsupported"))
+        .help(tr("help.switch_mode")),
+      Domain(_) => d.primary(loc, tr("label.domain")),
+      UndefinedVariable(ref name) => d
+        .primary(loc, tr("label.not_defined"))
+        .help(tr_with("help.assign_first", &[name])),
+      UnknownFunction(_) => {
+        let names: Vec<_> = builtins::FUNCS.iter().map(|f| f.name()).collect();
+        d.primary(loc, tr("label.not_builtin"))
+          .note(tr_with("note.functions", &[&names.join(", ")]))
+      }
+      ArityMismatch { found, .. } => d.primary(
+        loc,
+        tr_with("label.called_with", &[&found, &tr(arguments(found))]),
+      ),
+      SyntaxError => d.primary(loc, tr("label.not_parsed")),
+      ReadOnly(_) => d
+        .primary(loc, tr("label.read_only"))
+        .help(tr("help.another_name")),
+      NotDifferentiable(_) => d.primary(loc, tr("label.not_differentiable")),
+      NoSignChange { .. } => d
+        .primary(loc, tr("label.same_sign"))
+        .help(tr("help.opposite_signs")),
+      NoConvergence(_) => d
+        .primary(loc, tr("label.no_root"))
+        .help(tr("help.another_start")),
+      LimitExceeded(Limit::Depth(_)) => d
+        .primary(loc, tr("label.too_deep"))
+        .help(tr("help.split_expression")),
+      LimitExceeded(_) => d.primary(loc, tr("label.over_limit")),
+      NotVariable(_) => d.primary(loc, tr("label.not_variable")),
+    }
+  }
+
+  pub fn show_diagnostic(&self, input: &str, expr: &Ast) {
+    show(self.diagnostic(expr), input);
+  }
+
+  pub fn to_json(&self, input: &str) -> Json {
+    json::error(
+      self.value.kind(),
+      self.value.code(),
+      self.to_string(),
+      &self.loc,
+      input,
+      self,
+    )
   }
 }
 
@@ -122,37 +358,115 @@ pub fn show_trace<E: StdError>(e: E) {
   eprintln!("{}", e);
   let mut source = e.source();
   while let Some(e) = source {
-    eprintln!("caused by {}", e);
+    eprintln!("{}", tr_with("trace.caused_by", &[&e]));
     source = e.source()
   }
 }
 
-use ast::parse;
-use lexer::lex;
+// the catalog key of "argument" in the number `n` has in English
+fn arguments(n: usize) -> &'static str {
+  if n == 1 {
+    "word.argument"
+  } else {
+    "word.arguments"
+  }
+}
+
+use ast::{parse_all_limited, parse_equation};
+use lexer::lex_all;
+use limits::Limits;
+use operator::OperatorTable;
 use std::str::FromStr;
 impl FromStr for Ast {
   type Err = Error;
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    let tokens = lex(s)?;
-    let ast = parse(tokens)?;
+    Ast::from_str_limited(s, &Limits::default())
+  }
+}
+
+impl Ast {
+  // parses as `from_str` does, within `limits` rather than the default ones
+  pub fn from_str_limited(s: &str, limits: &Limits) -> Result<Self, Error> {
+    whole(Ast::parse_limited(s, &OperatorTable::default(), limits))
+  }
 
-    Ok(ast)
+  // `lhs = rhs` as `lhs - rhs`, or an expression, as the first argument of `solve`
+  pub fn parse_equation(s: &str, limits: &Limits) -> Result<Self, Error> {
+    let table = OperatorTable::default();
+    let (tokens, lex_errors) = lex_all(s, &table);
+    let (ast, parse_errors) = parse_equation(tokens, &table, limits);
+    let mut errors: Vec<Error> = lex_errors.into_iter().map(Error::from).collect();
+    errors.extend(parse_errors.into_iter().map(Error::from));
+    whole((ast, errors))
+  }
+
+  // lexes and parses past errors, giving a tree with error nodes and every error found.
+  // what could not be lexed is left out, so the parser still sees the rest
+  pub fn parse_partial(s: &str, table: &OperatorTable) -> (Ast, Vec<Error>) {
+    Ast::parse_limited(s, table, &Limits::default())
+  }
+
+  // parses as `parse_partial` does, within `limits` rather than the default ones
+  pub fn parse_limited(s: &str, table: &OperatorTable, limits: &Limits) -> (Ast, Vec<Error>) {
+    let (tokens, lex_errors) = lex_all(s, table);
+    let (ast, parse_errors) = parse_all_limited(tokens, table, limits);
+    let mut errors: Vec<Error> = lex_errors.into_iter().map(Error::from).collect();
+    errors.extend(parse_errors.into_iter().map(Error::from));
+    (ast, errors)
+  }
+}
+
+// the tree, if it was parsed without an error
+fn whole((ast, mut errors): (Ast, Vec<Error>)) -> Result<Ast, Error> {
+  match errors.len() {
+    0 => Ok(ast),
+    1 => Err(errors.remove(0)),
+    _ => Err(Error::Multiple(errors)),
   }
 }
 
 pub mod lexer {
+  use super::bignum::BigInt;
+  use super::operator::OperatorTable;
   use super::Annot;
   use super::Loc;
+  use crate::i18n::{tr, tr_with};
 
-  #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+  #[derive(Debug, Clone, PartialEq)]
   pub enum TokenKind {
-    Number(u64), //[1-9][0-9]*
-    Plus,        // +
-    Minus,       // -
-    Asterisk,    // *
-    Slash,       // /
-    LParen,      // (
-    RParen,      // )
+    Number(BigInt), //[0-9]+
+    Float(f64),     // ([0-9]+.[0-9]*|.[0-9]+)([eE][+-]?[0-9]+)?
+    Plus,           // +
+    Minus,          // -
+    Asterisk,       // *
+    Slash,          // /
+    Caret,          // ^
+    DoubleAsterisk, // **
+    Bang,           // !
+    LParen,         // (
+    RParen,         // )
+    Ident(String),  // [a-zA-Z_][a-zA-Z0-9_]*
+    Equal,          // =
+    Comma,          // ,
+    Symbol(String), // an operator symbol registered in an `OperatorTable`
+  }
+
+  impl TokenKind {
+    // the spelling looked up in the operator table
+    pub fn symbol(&self) -> Option<&str> {
+      use self::TokenKind::*;
+      match self {
+        Plus => Some("+"),
+        Minus => Some("-"),
+        Asterisk => Some("*"),
+        Slash => Some("/"),
+        Caret => Some("^"),
+        DoubleAsterisk => Some("**"),
+        Bang => Some("!"),
+        Symbol(s) => Some(s),
+        _ => None,
+      }
+    }
   }
 
   use std::fmt;
@@ -162,20 +476,32 @@ pub mod lexer {
 
       match self {
         Number(n) => n.fmt(f),
+        Float(x) => write!(f, "{:?}", x),
         Plus => write!(f, "+"),
         Minus => write!(f, "-"),
         Asterisk => write!(f, "*"),
         Slash => write!(f, "/"),
+        Caret => write!(f, "^"),
+        DoubleAsterisk => write!(f, "**"),
+        Bang => write!(f, "!"),
         LParen => write!(f, "("),
         RParen => write!(f, ")"),
+        Ident(name) => write!(f, "{}", name),
+        Equal => write!(f, "="),
+        Comma => write!(f, ","),
+        Symbol(s) => write!(f, "{}", s),
       }
     }
   }
 
   pub type Token = Annot<TokenKind>;
   impl Token {
+    #[allow(dead_code)]
     fn number(n: u64, loc: Loc) -> Self {
-      Self::new(TokenKind::Number(n), loc)
+      Self::new(TokenKind::Number(BigInt::from(n)), loc)
+    }
+    fn float(x: f64, loc: Loc) -> Self {
+      Self::new(TokenKind::Float(x), loc)
     }
     fn plus(loc: Loc) -> Self {
       Self::new(TokenKind::Plus, loc)
@@ -189,17 +515,36 @@ pub mod lexer {
     fn slash(loc: Loc) -> Self {
       Self::new(TokenKind::Slash, loc)
     }
+    fn caret(loc: Loc) -> Self {
+      Self::new(TokenKind::Caret, loc)
+    }
+    fn double_asterisk(loc: Loc) -> Self {
+      Self::new(TokenKind::DoubleAsterisk, loc)
+    }
+    fn bang(loc: Loc) -> Self {
+      Self::new(TokenKind::Bang, loc)
+    }
     fn lparen(loc: Loc) -> Self {
       Self::new(TokenKind::LParen, loc)
     }
     fn rparen(loc: Loc) -> Self {
       Self::new(TokenKind::RParen, loc)
     }
+    fn ident(name: &str, loc: Loc) -> Self {
+      Self::new(TokenKind::Ident(name.to_string()), loc)
+    }
+    fn equal(loc: Loc) -> Self {
+      Self::new(TokenKind::Equal, loc)
+    }
+    fn comma(loc: Loc) -> Self {
+      Self::new(TokenKind::Comma, loc)
+    }
   }
 
   #[derive(Debug, Clone, PartialEq, Eq, Hash)]
   pub enum LexErrorKind {
     InvalidChar(char),
+    NumberTooLarge,
     Eof,
   }
   pub type LexError = Annot<LexErrorKind>;
@@ -207,50 +552,155 @@ pub mod lexer {
     fn invalid_char(c: char, loc: Loc) -> Self {
       Self::new(LexErrorKind::InvalidChar(c), loc)
     }
+    fn number_too_large(loc: Loc) -> Self {
+      Self::new(LexErrorKind::NumberTooLarge, loc)
+    }
     fn eof(loc: Loc) -> Self {
       Self::new(LexErrorKind::Eof, loc)
     }
   }
 
-  impl fmt::Display for LexError {
+  impl LexErrorKind {
+    // the variant name, for the JSON output
+    pub fn kind(&self) -> &'static str {
+      use self::LexErrorKind::*;
+      match self {
+        InvalidChar(_) => "InvalidChar",
+        NumberTooLarge => "NumberTooLarge",
+        Eof => "Eof",
+      }
+    }
+
+    // the stable code, explained by `codes::explain`
+    pub fn code(&self) -> &'static str {
+      use self::LexErrorKind::*;
+      match self {
+        InvalidChar(_) => "C0001",
+        NumberTooLarge => "C0002",
+        Eof => "C0003",
+      }
+    }
+  }
+
+  // the message alone, which diagnostics show next to the location
+  impl fmt::Display for LexErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
       use self::LexErrorKind::*;
-      let loc = &self.loc;
+      match self {
+        InvalidChar(c) => write!(f, "{}", tr_with("lex.invalid_char", &[&c])),
+        NumberTooLarge => write!(f, "{}", tr("lex.number_too_large")),
+        Eof => write!(f, "{}", tr("eof")),
+      }
+    }
+  }
+
+  impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
       match self.value {
-        InvalidChar(c) => write!(f, "{}: invalid char '{}'", loc, c),
-        Eof => write!(f, "End of file"),
+        LexErrorKind::Eof => write!(f, "{}", self.value),
+        _ => write!(f, "{}: {}", self.loc, self.value),
       }
     }
   }
 
   pub fn lex(input: &str) -> Result<Vec<Token>, LexError> {
+    first_error(lex_symbols(input, &[]))
+  }
+
+  // lexes the custom operator symbols of `table` as well
+  pub fn lex_with(input: &str, table: &OperatorTable) -> Result<Vec<Token>, LexError> {
+    first_error(lex_all(input, table))
+  }
+
+  fn first_error(
+    (tokens, mut errors): (Vec<Token>, Vec<LexError>),
+  ) -> Result<Vec<Token>, LexError> {
+    if errors.is_empty() {
+      Ok(tokens)
+    } else {
+      Err(errors.swap_remove(0))
+    }
+  }
+
+  // lexes past errors, skipping what could not be lexed, so all of them are reported at once
+  pub fn lex_all(input: &str, table: &OperatorTable) -> (Vec<Token>, Vec<LexError>) {
+    lex_symbols(input, &table.custom_symbols())
+  }
+
+  fn lex_symbols(input: &str, symbols: &[&str]) -> (Vec<Token>, Vec<LexError>) {
     let input = input.as_bytes();
     let mut tokens = Vec::new();
+    let mut errors = Vec::new();
     let mut pos = 0;
     macro_rules! lex_a_token {
       ($lexer:expr) => {{
-        let (tok, p) = $lexer?;
-        tokens.push(tok);
-        pos = p;
+        match $lexer {
+          Ok((tok, p)) => {
+            tokens.push(tok);
+            pos = p;
+          }
+          Err(e) => {
+            pos = std::cmp::max(e.loc.1, pos + 1);
+            errors.push(e);
+          }
+        }
       }};
     }
     while pos < input.len() {
+      if let Some(symbol) = match_symbol(input, pos, symbols) {
+        let end = pos + symbol.len();
+        tokens.push(Token::new(
+          TokenKind::Symbol(symbol.to_string()),
+          Loc(pos, end),
+        ));
+        pos = end;
+        continue;
+      }
       match input[pos] {
-        b'0'..=b'9' => lex_a_token!(Ok(lex_number(input, pos))),
+        b'0'..=b'9' | b'.' => lex_a_token!(lex_number(input, pos)),
         b'+' => lex_a_token!(lex_plus(input, pos)),
         b'-' => lex_a_token!(lex_minus(input, pos)),
         b'*' => lex_a_token!(lex_asterisk(input, pos)),
         b'/' => lex_a_token!(lex_slash(input, pos)),
+        b'^' => lex_a_token!(lex_caret(input, pos)),
+        b'!' => lex_a_token!(lex_bang(input, pos)),
         b'(' => lex_a_token!(lex_lparen(input, pos)),
         b')' => lex_a_token!(lex_rparen(input, pos)),
+        b'=' => lex_a_token!(lex_equal(input, pos)),
+        b',' => lex_a_token!(lex_comma(input, pos)),
+        b'a'..=b'z' | b'A'..=b'Z' | b'_' => lex_a_token!(Ok::<_, LexError>(lex_ident(input, pos))),
         b' ' | b'\n' | b'\t' => {
           let ((), p) = skip_spaces(input, pos);
           pos = p;
         }
-        b => return Err(LexError::invalid_char(b as char, Loc(pos, pos + 1))),
+        _ => {
+          let c = char_at(input, pos);
+          let end = pos + c.len_utf8();
+          errors.push(LexError::invalid_char(c, Loc(pos, end)));
+          pos = end;
+        }
       }
     }
-    Ok(tokens)
+    (tokens, errors)
+  }
+
+  // the longest of `symbols` starting at `pos`
+  fn match_symbol<'a>(input: &[u8], pos: usize, symbols: &[&'a str]) -> Option<&'a str> {
+    symbols
+      .iter()
+      .filter(|s| input[pos..].starts_with(s.as_bytes()))
+      .max_by_key(|s| s.len())
+      .copied()
+  }
+
+  // the whole character starting at `pos`, which is always at a character boundary as the
+  // lexer only ever steps over ASCII bytes or whole characters
+  fn char_at(input: &[u8], pos: usize) -> char {
+    let end = std::cmp::min(pos + 4, input.len());
+    String::from_utf8_lossy(&input[pos..end])
+      .chars()
+      .next()
+      .unwrap()
   }
 
   fn consume_byte(input: &[u8], pos: usize, b: u8) -> Result<(u8, usize), LexError> {
@@ -258,10 +708,8 @@ pub mod lexer {
       return Err(LexError::eof(Loc(pos, pos)));
     }
     if input[pos] != b {
-      return Err(LexError::invalid_char(
-        input[pos] as char,
-        Loc(pos, pos + 1),
-      ));
+      let c = char_at(input, pos);
+      return Err(LexError::invalid_char(c, Loc(pos, pos + c.len_utf8())));
     }
     Ok((b, pos + 1))
   }
@@ -272,25 +720,86 @@ pub mod lexer {
     consume_byte(input, start, b'-').map(|(_, end)| (Token::minus(Loc(start, end)), end))
   }
   fn lex_asterisk(input: &[u8], start: usize) -> Result<(Token, usize), LexError> {
-    consume_byte(input, start, b'*').map(|(_, end)| (Token::asterisk(Loc(start, end)), end))
+    let (_, end) = consume_byte(input, start, b'*')?;
+    // `**` is the power operator, an alternative spelling of `^`
+    match consume_byte(input, end, b'*') {
+      Ok((_, end)) => Ok((Token::double_asterisk(Loc(start, end)), end)),
+      Err(_) => Ok((Token::asterisk(Loc(start, end)), end)),
+    }
   }
   fn lex_slash(input: &[u8], start: usize) -> Result<(Token, usize), LexError> {
     consume_byte(input, start, b'/').map(|(_, end)| (Token::slash(Loc(start, end)), end))
   }
+  fn lex_caret(input: &[u8], start: usize) -> Result<(Token, usize), LexError> {
+    consume_byte(input, start, b'^').map(|(_, end)| (Token::caret(Loc(start, end)), end))
+  }
+  fn lex_bang(input: &[u8], start: usize) -> Result<(Token, usize), LexError> {
+    consume_byte(input, start, b'!').map(|(_, end)| (Token::bang(Loc(start, end)), end))
+  }
   fn lex_lparen(input: &[u8], start: usize) -> Result<(Token, usize), LexError> {
     consume_byte(input, start, b'(').map(|(_, end)| (Token::lparen(Loc(start, end)), end))
   }
   fn lex_rparen(input: &[u8], start: usize) -> Result<(Token, usize), LexError> {
     consume_byte(input, start, b')').map(|(_, end)| (Token::rparen(Loc(start, end)), end))
   }
-  fn lex_number(input: &[u8], mut pos: usize) -> (Token, usize) {
+  fn lex_equal(input: &[u8], start: usize) -> Result<(Token, usize), LexError> {
+    consume_byte(input, start, b'=').map(|(_, end)| (Token::equal(Loc(start, end)), end))
+  }
+  fn lex_comma(input: &[u8], start: usize) -> Result<(Token, usize), LexError> {
+    consume_byte(input, start, b',').map(|(_, end)| (Token::comma(Loc(start, end)), end))
+  }
+  fn lex_ident(input: &[u8], start: usize) -> (Token, usize) {
+    use std::str::from_utf8;
+    let mut pos = start;
+    while pos < input.len() && (input[pos].is_ascii_alphanumeric() || input[pos] == b'_') {
+      pos += 1;
+    }
+    let name = from_utf8(&input[start..pos]).unwrap();
+    (Token::ident(name, Loc(start, pos)), pos)
+  }
+  fn lex_number(input: &[u8], start: usize) -> Result<(Token, usize), LexError> {
     use std::str::from_utf8;
-    let start = pos;
-    while pos < input.len() && b"1234556780".contains(&input[pos]) {
+    let mut pos = skip_digits(input, start);
+    let mut is_float = false;
+    if pos < input.len() && input[pos] == b'.' {
+      is_float = true;
+      pos = skip_digits(input, pos + 1);
+      // a lone '.' is not a number
+      if pos == start + 1 {
+        return Err(LexError::invalid_char('.', Loc(start, pos)));
+      }
+    }
+    // the exponent part is taken only when digits follow, so `1e` lexes as `1` and `e`
+    if pos < input.len() && b"eE".contains(&input[pos]) {
+      let mut p = pos + 1;
+      if p < input.len() && b"+-".contains(&input[p]) {
+        p += 1;
+      }
+      let end = skip_digits(input, p);
+      if p < end {
+        is_float = true;
+        pos = end;
+      }
+    }
+    let s = from_utf8(&input[start..pos]).unwrap();
+    let loc = Loc(start, pos);
+    let token = if is_float {
+      // a float literal never fails to parse, it becomes infinity instead
+      match s.parse::<f64>().unwrap() {
+        x if x.is_infinite() => return Err(LexError::number_too_large(loc)),
+        x => Token::float(x, loc),
+      }
+    } else {
+      // digits only, so this never fails and never overflows
+      Token::new(TokenKind::Number(s.parse().unwrap()), loc)
+    };
+    Ok((token, pos))
+  }
+  fn skip_digits(input: &[u8], mut pos: usize) -> usize {
+    while pos < input.len() && input[pos].is_ascii_digit() {
       pos += 1;
     }
-    let n = from_utf8(&input[start..pos]).unwrap().parse().unwrap();
-    (Token::number(n, Loc(start, pos)), pos)
+    pos
   }
   fn skip_spaces(input: &[u8], mut pos: usize) -> ((), usize) {
     while pos < input.len() && b" \n\t".contains(&input[pos]) {
@@ -323,37 +832,346 @@ pub mod lexer {
       ])
     );
     assert_eq!(
-      lex("1 + 2 + a"),
-      Err(LexError::invalid_char('a', Loc(8, 9))),
+      lex("1 + 2 + $"),
+      Err(LexError::invalid_char('$', Loc(8, 9))),
+    );
+    assert_eq!(
+      lex("x_1 = ans2*2"),
+      Ok(vec![
+        Token::ident("x_1", Loc(0, 3)),
+        Token::equal(Loc(4, 5)),
+        Token::ident("ans2", Loc(6, 10)),
+        Token::asterisk(Loc(10, 11)),
+        Token::number(2, Loc(11, 12)),
+      ])
+    );
+    assert_eq!(
+      lex("2.75 .5 1e-9 6.02E23 2."),
+      Ok(vec![
+        Token::float(2.75, Loc(0, 4)),
+        Token::float(0.5, Loc(5, 7)),
+        Token::float(1e-9, Loc(8, 12)),
+        Token::float(6.02e23, Loc(13, 20)),
+        Token::float(2.0, Loc(21, 23)),
+      ])
+    );
+    assert_eq!(
+      lex("1e"),
+      Ok(vec![
+        Token::number(1, Loc(0, 1)),
+        Token::ident("e", Loc(1, 2))
+      ])
+    );
+    assert_eq!(lex("1 + ."), Err(LexError::invalid_char('.', Loc(4, 5))));
+    assert_eq!(
+      lex("1 + 18446744073709551616"),
+      Ok(vec![
+        Token::number(1, Loc(0, 1)),
+        Token::plus(Loc(2, 3)),
+        Token::new(
+          TokenKind::Number("18446744073709551616".parse().unwrap()),
+          Loc(4, 24)
+        ),
+      ])
+    );
+    assert_eq!(lex("1e400"), Err(LexError::number_too_large(Loc(0, 5))));
+    assert_eq!(
+      lex_all("1 $ 2 # 1e400 + .", &OperatorTable::default()),
+      (
+        vec![
+          Token::number(1, Loc(0, 1)),
+          Token::number(2, Loc(4, 5)),
+          Token::plus(Loc(14, 15)),
+        ],
+        vec![
+          LexError::invalid_char('$', Loc(2, 3)),
+          LexError::invalid_char('#', Loc(6, 7)),
+          LexError::number_too_large(Loc(8, 13)),
+          LexError::invalid_char('.', Loc(16, 17)),
+        ]
+      )
+    );
+    // a multi-byte character is reported whole, with all of its bytes
+    assert_eq!(
+      lex_all("1 ＋ 2 😀", &OperatorTable::default()),
+      (
+        vec![Token::number(1, Loc(0, 1)), Token::number(2, Loc(6, 7))],
+        vec![
+          LexError::invalid_char('＋', Loc(2, 5)),
+          LexError::invalid_char('😀', Loc(8, 12)),
+        ]
+      )
+    );
+    assert_eq!(
+      lex("2^3**4*5"),
+      Ok(vec![
+        Token::number(2, Loc(0, 1)),
+        Token::caret(Loc(1, 2)),
+        Token::number(3, Loc(2, 3)),
+        Token::double_asterisk(Loc(3, 5)),
+        Token::number(4, Loc(5, 6)),
+        Token::asterisk(Loc(6, 7)),
+        Token::number(5, Loc(7, 8)),
+      ])
     );
   }
 }
 
 pub mod ast {
+  use super::bignum::BigInt;
   use super::Annot;
   use super::Loc;
+  use crate::i18n::{tr, tr_with};
 
-  #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+  #[derive(Debug)]
   pub enum AstKind {
-    Num(u64),
+    Num(BigInt),
+    Float(f64),
+    Var(String),
+    Assign { name: String, e: Box<Ast> },
+    Call { name: String, args: Vec<Ast> },
     UniOp { op: UniOp, e: Box<Ast> },
-    BinOp { op: BinOp, r: Box<Ast>, l: Box<Ast> },
+    BinOp { op: BinOp, l: Box<Ast>, r: Box<Ast> },
+    // where the parser could not make out an expression
+    Error,
   }
   pub type Ast = Annot<AstKind>;
+
+  // dropping the boxes one inside another would recurse as deep as the tree, so the
+  // children are moved out onto a stack first, leaving each node none to drop in turn
+  impl Drop for AstKind {
+    fn drop(&mut self) {
+      let mut stack = Vec::new();
+      take_children(self, &mut stack);
+      while let Some(mut ast) = stack.pop() {
+        take_children(&mut ast.value, &mut stack);
+      }
+    }
+  }
+
+  fn take_children(kind: &mut AstKind, stack: &mut Vec<Ast>) {
+    let mut take = |e: &mut Box<Ast>| {
+      stack.push(std::mem::replace(e.as_mut(), Ast::error(Loc(0, 0))));
+    };
+    match kind {
+      AstKind::Assign { e, .. } | AstKind::UniOp { e, .. } => take(e),
+      AstKind::BinOp { l, r, .. } => {
+        take(l);
+        take(r);
+      }
+      AstKind::Call { args, .. } => stack.append(args),
+      _ => {}
+    }
+  }
+
+  // derived, cloning and comparing would recurse as deep as the tree as well, so they
+  // walk it instead
+  impl Clone for AstKind {
+    fn clone(&self) -> Self {
+      let children = self
+        .children()
+        .into_iter()
+        .map(|child| {
+          child
+            .fold(|node, children| Ast::new(node.value.with_children(children), node.loc.clone()))
+        })
+        .collect();
+      self.with_children(children)
+    }
+  }
+
+  impl PartialEq for AstKind {
+    fn eq(&self, other: &Self) -> bool {
+      alike(self, other, true)
+    }
+  }
+
+  // whether two trees are the same node for node, and in the same places in the source
+  // if `locs` is true
+  pub(super) fn alike(a: &AstKind, b: &AstKind, locs: bool) -> bool {
+    let mut pairs = vec![(a, b)];
+    while let Some((a, b)) = pairs.pop() {
+      if !a.same_node(b, locs) {
+        return false;
+      }
+      for i in 0.. {
+        match (a.child(i), b.child(i)) {
+          (Some(x), Some(y)) if !locs || x.loc == y.loc => pairs.push((&x.value, &y.value)),
+          (None, None) => break,
+          _ => return false,
+        }
+      }
+    }
+    true
+  }
+
+  impl AstKind {
+    // the `i`th child of a node, in the order they are evaluated
+    pub(super) fn child(&self, i: usize) -> Option<&Ast> {
+      match (self, i) {
+        (AstKind::Assign { e, .. }, 0) | (AstKind::UniOp { e, .. }, 0) => Some(e),
+        (AstKind::BinOp { l, .. }, 0) => Some(l),
+        (AstKind::BinOp { r, .. }, 1) => Some(r),
+        (AstKind::Call { args, .. }, i) => args.get(i),
+        _ => None,
+      }
+    }
+
+    pub(super) fn children(&self) -> Vec<&Ast> {
+      (0..).map_while(|i| self.child(i)).collect()
+    }
+
+    // a copy of the node with `children` in place of its own
+    fn with_children(&self, children: Vec<Ast>) -> AstKind {
+      let mut children = children.into_iter();
+      let mut next = || Box::new(children.next().unwrap());
+      match self {
+        AstKind::Num(n) => AstKind::Num(n.clone()),
+        AstKind::Float(x) => AstKind::Float(*x),
+        AstKind::Var(name) => AstKind::Var(name.clone()),
+        AstKind::Assign { name, .. } => AstKind::Assign {
+          name: name.clone(),
+          e: next(),
+        },
+        AstKind::UniOp { op, .. } => AstKind::UniOp {
+          op: op.clone(),
+          e: next(),
+        },
+        AstKind::BinOp { op, .. } => AstKind::BinOp {
+          op: op.clone(),
+          l: next(),
+          r: next(),
+        },
+        AstKind::Call { name, .. } => AstKind::Call {
+          name: name.clone(),
+          args: children.collect(),
+        },
+        AstKind::Error => AstKind::Error,
+      }
+    }
+
+    // whether two nodes are the same but for their children, and for where their
+    // operators are in the source unless `locs` is true
+    fn same_node(&self, other: &AstKind, locs: bool) -> bool {
+      use self::AstKind::*;
+      match (self, other) {
+        (Num(m), Num(n)) => m == n,
+        (Float(x), Float(y)) => x == y,
+        (Var(m), Var(n)) => m == n,
+        (Assign { name: m, .. }, Assign { name: n, .. }) => m == n,
+        (Call { name: m, args: xs }, Call { name: n, args: ys }) => m == n && xs.len() == ys.len(),
+        (UniOp { op: p, .. }, UniOp { op: q, .. }) => {
+          p.value == q.value && (!locs || p.loc == q.loc)
+        }
+        (BinOp { op: p, .. }, BinOp { op: q, .. }) => {
+          p.value == q.value && (!locs || p.loc == q.loc)
+        }
+        (Error, Error) => true,
+        _ => false,
+      }
+    }
+  }
+
   impl Ast {
-    #[allow(dead_code)]
-    fn num(n: u64, loc: Loc) -> Self {
-      Self::new(AstKind::Num(n), loc)
+    pub fn num(n: u64, loc: Loc) -> Self {
+      Self::new(AstKind::Num(BigInt::from(n)), loc)
+    }
+    pub fn var(name: &str, loc: Loc) -> Self {
+      Self::new(AstKind::Var(name.to_string()), loc)
+    }
+    pub fn assign(name: String, e: Ast, loc: Loc) -> Self {
+      Self::new(
+        AstKind::Assign {
+          name,
+          e: Box::new(e),
+        },
+        loc,
+      )
+    }
+    pub fn call(name: &str, args: Vec<Ast>, loc: Loc) -> Self {
+      Self::new(
+        AstKind::Call {
+          name: name.to_string(),
+          args,
+        },
+        loc,
+      )
+    }
+    pub fn error(loc: Loc) -> Self {
+      Self::new(AstKind::Error, loc)
+    }
+
+    // the outermost node spanning exactly `loc`, the first in the order of evaluation
+    pub(super) fn find(&self, loc: &Loc) -> Option<&Ast> {
+      let mut stack = vec![self];
+      while let Some(ast) = stack.pop() {
+        if ast.loc == *loc {
+          return Some(ast);
+        }
+        stack.extend(ast.value.children().into_iter().rev());
+      }
+      None
+    }
+
+    // folds the tree into one value from the leaves up, with a stack of its own rather
+    // than recursing. `enter` may give the value of a node before its children, which are
+    // then skipped, and otherwise `exit` gives it from theirs. the first error of either
+    // ends the walk
+    pub(super) fn walk<'a, T, E>(
+      &'a self,
+      mut enter: impl FnMut(&'a Ast) -> Result<Option<T>, E>,
+      mut exit: impl FnMut(&'a Ast, Vec<T>) -> Result<T, E>,
+    ) -> Result<T, E> {
+      // the nodes whose children are being walked, with the values of those done
+      let mut pending: Vec<(&Ast, Vec<T>)> = Vec::new();
+      let mut node = self;
+      loop {
+        let mut value = match enter(node)? {
+          Some(value) => value,
+          None => match node.value.child(0) {
+            Some(first) => {
+              pending.push((node, Vec::new()));
+              node = first;
+              continue;
+            }
+            None => exit(node, Vec::new())?,
+          },
+        };
+        loop {
+          let (parent, values) = match pending.last_mut() {
+            Some((parent, values)) => (*parent, values),
+            None => return Ok(value),
+          };
+          values.push(value);
+          if let Some(next) = parent.value.child(values.len()) {
+            node = next;
+            break;
+          }
+          let values = pending.pop().unwrap().1;
+          value = exit(parent, values)?;
+        }
+      }
+    }
+
+    // `walk` through every node where nothing fails
+    pub(super) fn fold<'a, T>(&'a self, mut f: impl FnMut(&'a Ast, Vec<T>) -> T) -> T {
+      let folded: Result<T, std::convert::Infallible> =
+        self.walk(|_| Ok(None), |node, values| Ok(f(node, values)));
+      match folded {
+        Ok(value) => value,
+        Err(never) => match never {},
+      }
     }
-    fn uniop(op: UniOp, e: Ast, loc: Loc) -> Self {
+
+    pub fn uniop(op: UniOp, e: Ast, loc: Loc) -> Self {
       Self::new(AstKind::UniOp { op, e: Box::new(e) }, loc)
     }
-    fn binop(op: BinOp, r: Ast, l: Ast, loc: Loc) -> Self {
+    pub fn binop(op: BinOp, l: Ast, r: Ast, loc: Loc) -> Self {
       Self::new(
         AstKind::BinOp {
           op,
-          r: Box::new(r),
           l: Box::new(l),
+          r: Box::new(r),
         },
         loc,
       )
@@ -367,10 +1185,10 @@ pub mod ast {
   }
   pub type UniOp = Annot<UniOpKind>;
   impl UniOp {
-    fn plus(loc: Loc) -> Self {
+    pub fn plus(loc: Loc) -> Self {
       Self::new(UniOpKind::Plus, loc)
     }
-    fn minus(loc: Loc) -> Self {
+    pub fn minus(loc: Loc) -> Self {
       Self::new(UniOpKind::Minus, loc)
     }
   }
@@ -381,198 +1199,731 @@ pub mod ast {
     Sub,
     Mult,
     Div,
+    Pow,
   }
   pub type BinOp = Annot<BinOpKind>;
   impl BinOp {
-    fn add(loc: Loc) -> Self {
+    pub fn add(loc: Loc) -> Self {
       Self::new(BinOpKind::Add, loc)
     }
-    fn sub(loc: Loc) -> Self {
+    pub fn sub(loc: Loc) -> Self {
       Self::new(BinOpKind::Sub, loc)
     }
-    fn mult(loc: Loc) -> Self {
+    pub fn mult(loc: Loc) -> Self {
       Self::new(BinOpKind::Mult, loc)
     }
-    fn div(loc: Loc) -> Self {
+    pub fn div(loc: Loc) -> Self {
       Self::new(BinOpKind::Div, loc)
     }
+    pub fn pow(loc: Loc) -> Self {
+      Self::new(BinOpKind::Pow, loc)
+    }
   }
 
   use super::lexer::Token;
   #[allow(dead_code)]
-  #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+  #[derive(Debug, Clone, PartialEq)]
   pub enum ParseError {
     UnexpectedToken(Token),
     NotExpression(Token),
     NotOperator(Token),
     UnclosedOpenParen(Token),
     RedundantExpression(Token),
+    NotAssignable(Token),
+    // nested deeper than the limit, at the token going past it
+    TooDeep(Token, usize),
     Eof,
   }
 
   use std::fmt;
-  impl fmt::Display for ParseError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+  impl ParseError {
+    // the token the error is about, which is all but `Eof`
+    pub fn token(&self) -> Option<&Token> {
       use self::ParseError::*;
       match self {
-        UnexpectedToken(token) => write!(f, "{}: '{}' is not expected", token.loc, token.value),
-        NotExpression(token) => write!(
-          f,
-          "{}: '{}' is not a start of expression",
-          token.loc, token.value
-        ),
-        NotOperator(token) => write!(f, "{}: '{}' is not an operator", token.loc, token.value),
-        UnclosedOpenParen(token) => write!(f, "{}: '{}' is not closed", token.loc, token.value),
-        RedundantExpression(token) => write!(
-          f,
-          "{}: expression after '{}' is redundant",
-          token.loc, token.value
-        ),
-        Eof => write!(f, "End of file"),
+        UnexpectedToken(token)
+        | NotExpression(token)
+        | NotOperator(token)
+        | UnclosedOpenParen(token)
+        | RedundantExpression(token)
+        | NotAssignable(token)
+        | TooDeep(token, _) => Some(token),
+        Eof => None,
       }
     }
-  }
 
-  pub fn parse(tokens: Vec<Token>) -> Result<Ast, ParseError> {
-    let mut tokens = tokens.into_iter().peekable();
-    let ret = parse_expr(&mut tokens)?;
-    match tokens.next() {
-      Some(token) => Err(ParseError::RedundantExpression(token)),
-      None => Ok(ret),
-    }
+    // the variant name, for the JSON output
+    pub fn kind(&self) -> &'static str {
+      use self::ParseError::*;
+      match self {
+        UnexpectedToken(_) => "UnexpectedToken",
+        NotExpression(_) => "NotExpression",
+        NotOperator(_) => "NotOperator",
+        UnclosedOpenParen(_) => "UnclosedOpenParen",
+        RedundantExpression(_) => "RedundantExpression",
+        NotAssignable(_) => "NotAssignable",
+        TooDeep(..) => "TooDeep",
+        Eof => "Eof",
+      }
+    }
+
+    // the stable code, explained by `codes::explain`
+    pub fn code(&self) -> &'static str {
+      use self::ParseError::*;
+      match self {
+        UnexpectedToken(_) => "C0101",
+        NotExpression(_) => "C0102",
+        NotOperator(_) => "C0103",
+        UnclosedOpenParen(_) => "C0104",
+        RedundantExpression(_) => "C0105",
+        NotAssignable(_) => "C0106",
+        Eof => "C0107",
+        TooDeep(..) => "C0108",
+      }
+    }
+
+    // the message without the location
+    pub fn message(&self) -> String {
+      use self::ParseError::*;
+      match self {
+        UnexpectedToken(token) => tr_with("parse.unexpected_token", &[&token.value]),
+        NotExpression(token) => tr_with("parse.not_expression", &[&token.value]),
+        NotOperator(token) => tr_with("parse.not_operator", &[&token.value]),
+        UnclosedOpenParen(token) => tr_with("parse.unclosed_paren", &[&token.value]),
+        RedundantExpression(token) => tr_with("parse.redundant", &[&token.value]),
+        NotAssignable(token) => tr_with("parse.not_assignable", &[&token.value]),
+        TooDeep(token, depth) => tr_with("parse.too_deep", &[&token.value, depth]),
+        Eof => tr("eof").to_string(),
+      }
+    }
   }
 
-  use std::iter::Peekable;
-  fn parse_expr<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Ast, ParseError>
-  where
-    Tokens: Iterator<Item = Token>,
-  {
-    parse_expr3(tokens)
+  // the expression in infix, with only the parentheses the precedences need. what is
+  // left to write is kept on a stack, last first, rather than recursing
+  impl fmt::Display for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      let mut stack = vec![Write::Ast(self, 0)];
+      while let Some(write) = stack.pop() {
+        let (ast, min) = match write {
+          Write::Ast(ast, min) => (ast, min),
+          Write::Str(s) => {
+            f.write_str(s)?;
+            continue;
+          }
+        };
+        let prec = precedence(ast);
+        if prec < min {
+          stack.extend([Write::Str(")"), Write::Ast(ast, 0), Write::Str("(")]);
+          continue;
+        }
+        match ast.value {
+          AstKind::Num(ref n) => write!(f, "{}", n)?,
+          AstKind::Float(x) => write!(f, "{:?}", x)?,
+          AstKind::Var(ref name) => write!(f, "{}", name)?,
+          AstKind::Assign { ref name, ref e } => {
+            write!(f, "{} = ", name)?;
+            stack.push(Write::Ast(e, 0));
+          }
+          AstKind::Call { ref name, ref args } => {
+            write!(f, "{}(", name)?;
+            stack.push(Write::Str(")"));
+            for (i, arg) in args.iter().enumerate().rev() {
+              stack.push(Write::Ast(arg, 0));
+              if i > 0 {
+                stack.push(Write::Str(", "));
+              }
+            }
+          }
+          AstKind::UniOp { ref op, ref e } => {
+            match op.value {
+              UniOpKind::Plus => write!(f, "+")?,
+              UniOpKind::Minus => write!(f, "-")?,
+            }
+            stack.push(Write::Ast(e, prec));
+          }
+          AstKind::BinOp {
+            ref op,
+            ref l,
+            ref r,
+          } => {
+            let symbol = match op.value {
+              BinOpKind::Add => " + ",
+              BinOpKind::Sub => " - ",
+              BinOpKind::Mult => " * ",
+              BinOpKind::Div => " / ",
+              BinOpKind::Pow => " ^ ",
+            };
+            // `^` groups to the right, the others to the left
+            let (l_min, r_min) = match op.value {
+              BinOpKind::Pow => (prec + 1, prec),
+              _ => (prec, prec + 1),
+            };
+            stack.extend([
+              Write::Ast(r, r_min),
+              Write::Str(symbol),
+              Write::Ast(l, l_min),
+            ]);
+          }
+          AstKind::Error => write!(f, "?")?,
+        }
+      }
+      Ok(())
+    }
   }
 
-  use super::lexer::TokenKind;
-  fn parse_expr3<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Ast, ParseError>
-  where
-    Tokens: Iterator<Item = Token>,
-  {
-    fn parse_expr3_op<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<BinOp, ParseError>
-    where
-      Tokens: Iterator<Item = Token>,
-    {
-      let op = tokens
-        .peek()
-        .ok_or(ParseError::Eof)
-        .and_then(|token| match token.value {
-          TokenKind::Plus => Ok(BinOp::add(token.loc.clone())),
-          TokenKind::Minus => Ok(BinOp::sub(token.loc.clone())),
-          _ => Err(ParseError::NotOperator(token.clone())),
-        })?;
-      tokens.next();
-      Ok(op)
-    }
-
-    parse_left_binop(tokens, parse_expr2, parse_expr3_op)
-  }
-
-  fn parse_left_binop<Tokens>(
-    tokens: &mut Peekable<Tokens>,
-    subexpr_parser: fn(&mut Peekable<Tokens>) -> Result<Ast, ParseError>,
-    op_parser: fn(&mut Peekable<Tokens>) -> Result<BinOp, ParseError>,
-  ) -> Result<Ast, ParseError>
-  where
-    Tokens: Iterator<Item = Token>,
-  {
-    let mut l = subexpr_parser(tokens)?;
-
-    while tokens.peek().is_some() {
-      let op = match op_parser(tokens) {
-        Ok(op) => op,
-        Err(_) => break, // no more infix op
-      };
-      let r = subexpr_parser(tokens)?;
-      let loc = l.loc.merge(&r.loc);
-      l = Ast::binop(op, l, r, loc)
-    }
-
-    Ok(l)
-  }
-
-  fn parse_expr2<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Ast, ParseError>
-  where
-    Tokens: Iterator<Item = Token>,
-  {
-    let mut e = parse_expr1(tokens)?;
-    loop {
-      match tokens.peek().map(|token| token.value) {
-        Some(TokenKind::Asterisk) | Some(TokenKind::Slash) => {
-          let op = match tokens.next().unwrap() {
-            Token {
-              value: TokenKind::Asterisk,
-              loc,
-            } => BinOp::mult(loc),
-            Token {
-              value: TokenKind::Slash,
-              loc,
-            } => BinOp::div(loc),
-            _ => unreachable!(),
-          };
+  // a node to write where its precedence is at least the one given, or some text
+  enum Write<'a> {
+    Ast(&'a Ast, u32),
+    Str(&'static str),
+  }
+
+  // how tightly a node binds, as the default operator table has it
+  fn precedence(ast: &Ast) -> u32 {
+    match ast.value {
+      AstKind::Assign { .. } => 0,
+      AstKind::BinOp { ref op, .. } => match op.value {
+        BinOpKind::Add | BinOpKind::Sub => 10,
+        BinOpKind::Mult | BinOpKind::Div => 20,
+        BinOpKind::Pow => 40,
+      },
+      // a negative literal reads as a negation
+      AstKind::UniOp { .. } => 30,
+      AstKind::Num(ref n) if n.is_negative() => 30,
+      AstKind::Float(x) if x.is_sign_negative() => 30,
+      _ => 50,
+    }
+  }
+
+  impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self.token() {
+        Some(token) => write!(f, "{}: {}", token.loc, self.message()),
+        None => write!(f, "{}", self.message()),
+      }
+    }
+  }
+
+  pub fn parse(tokens: Vec<Token>) -> Result<Ast, ParseError> {
+    parse_with(tokens, &OperatorTable::default())
+  }
+
+  // parses with the operators of `table`, which an embedder may have extended
+  pub fn parse_with(tokens: Vec<Token>, table: &OperatorTable) -> Result<Ast, ParseError> {
+    parse_limited(tokens, table, &Limits::default())
+  }
+
+  // parses as `parse_with` does, within `limits` rather than the default ones
+  pub fn parse_limited(
+    tokens: Vec<Token>,
+    table: &OperatorTable,
+    limits: &Limits,
+  ) -> Result<Ast, ParseError> {
+    let (ast, mut errors) = parse_all_limited(tokens, table, limits);
+    if errors.is_empty() {
+      Ok(ast)
+    } else {
+      Err(errors.swap_remove(0))
+    }
+  }
+
+  // parses past errors, so all of them are reported at once. the tree is still built,
+  // with `AstKind::Error` where an expression could not be parsed
+  pub fn parse_all(tokens: Vec<Token>, table: &OperatorTable) -> (Ast, Vec<ParseError>) {
+    parse_all_limited(tokens, table, &Limits::default())
+  }
+
+  // parses past errors as `parse_all` does, giving up on the rest of the input where it
+  // nests deeper than `limits.depth`
+  pub fn parse_all_limited(
+    tokens: Vec<Token>,
+    table: &OperatorTable,
+    limits: &Limits,
+  ) -> (Ast, Vec<ParseError>) {
+    parse_goal(tokens, table, limits, Goal::Expr)
+  }
+
+  // parses past errors as `parse_all_limited` does, taking `lhs = rhs` as the equation
+  // `lhs - rhs` rather than as an assignment, as the first argument of `solve` is
+  pub fn parse_equation(
+    tokens: Vec<Token>,
+    table: &OperatorTable,
+    limits: &Limits,
+  ) -> (Ast, Vec<ParseError>) {
+    parse_goal(tokens, table, limits, Goal::Equation)
+  }
 
-          let r = parse_expr1(tokens)?;
-          let loc = e.loc.merge(&r.loc);
-          e = Ast::binop(op, e, r, loc);
+  fn parse_goal(
+    tokens: Vec<Token>,
+    table: &OperatorTable,
+    limits: &Limits,
+    goal: Goal,
+  ) -> (Ast, Vec<ParseError>) {
+    let mut parser = Parser {
+      tokens: tokens.into_iter().peekable(),
+      table,
+      errors: Vec::new(),
+      end: 0,
+      depth: 0,
+      level: 0,
+      max_level: limits.depth,
+      gave_up: false,
+    };
+    let ret = parser.parse(goal);
+    if let Some(token) = parser.tokens.peek().cloned() {
+      parser.error(ParseError::RedundantExpression(token));
+      // the rest is redundant as a whole, but may hold more errors
+      while let Some(token) = parser.tokens.peek() {
+        match token.value {
+          TokenKind::RParen => {
+            parser.next();
+          }
+          _ => {
+            parser.parse_expr();
+          }
         }
-        _ => return Ok(e),
       }
     }
+    (ret, parser.errors)
+  }
+
+  use super::lexer::TokenKind;
+  use super::limits::Limits;
+  use super::operator::{Action, Assoc, Fixity, Operator, OperatorTable};
+  use std::iter::Peekable;
+  struct Parser<'a, Tokens: Iterator<Item = Token>> {
+    tokens: Peekable<Tokens>,
+    table: &'a OperatorTable,
+    errors: Vec<ParseError>,
+    // the end of the last token taken, where a missing expression at the end goes
+    end: usize,
+    // the number of open parentheses, which leave a stray `)` or `,` to the one closing them
+    depth: usize,
+    // how deeply the node being parsed nests in the tree, up to `max_level`
+    level: usize,
+    max_level: usize,
+    // set on going past `max_level`, after which the input is skipped without errors
+    gave_up: bool,
+  }
+
+  // what the parser goes on to parse
+  enum Goal {
+    Expr,
+    // `lhs = rhs` as `lhs - rhs`, or an expression, as `solve` takes its first argument
+    Equation,
+    // an expression whose operators all bind tighter than the power
+    Bp(u32),
+  }
+
+  enum Step {
+    Parse(Goal),
+    // an expression parsed, for the frame waiting for it
+    Done(Ast),
   }
 
-  fn parse_expr1<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Ast, ParseError>
-  where
-    Tokens: Iterator<Item = Token>,
-  {
-    match tokens.peek().map(|token| token.value) {
-      Some(TokenKind::Plus) | Some(TokenKind::Minus) => {
-        let op = match tokens.next() {
-          Some(Token {
-            value: TokenKind::Plus,
-            loc,
-          }) => UniOp::plus(loc),
-          Some(Token {
-            value: TokenKind::Minus,
-            loc,
-          }) => UniOp::minus(loc),
-          _ => unreachable!(),
+  // a node whose child is being parsed, where a recursive descent would be in a call
+  enum Frame {
+    // an expression, which may be the left hand side of `=`
+    Expr,
+    // the right hand side of `=`, assigned to the name if the left one was a variable
+    Assign {
+      name: Option<String>,
+      loc: Loc,
+    },
+    // an expression, which may be the left hand side of an equation
+    Equation,
+    // the right hand side of an equation, after the left one and the `=`
+    Rhs {
+      l: Ast,
+      eq: Loc,
+    },
+    // the first operand of an expression of operators, with the power they must beat and
+    // where in the source it starts, before any parenthesis around it
+    Bp {
+      min_bp: u32,
+      start: usize,
+    },
+    Prefix {
+      op: Operator,
+      op_loc: Loc,
+    },
+    // the right operand, with the power, the level and the start of the operators it is
+    // among
+    Infix {
+      op: Operator,
+      op_loc: Loc,
+      l: Ast,
+      min_bp: u32,
+      start: usize,
+      level: usize,
+    },
+    // the expression in parentheses, after the `(`
+    Paren(Token),
+    // an argument of a call, after the ones before it
+    Args {
+      name: String,
+      loc: Loc,
+      lparen: Token,
+      args: Vec<Ast>,
+    },
+  }
+
+  impl<'a, Tokens: Iterator<Item = Token>> Parser<'a, Tokens> {
+    fn next(&mut self) -> Option<Token> {
+      let token = self.tokens.next()?;
+      self.end = token.loc.1;
+      Some(token)
+    }
+
+    fn peek_kind(&mut self) -> Option<&TokenKind> {
+      self.tokens.peek().map(|token| &token.value)
+    }
+
+    fn error(&mut self, e: ParseError) {
+      if !self.gave_up {
+        self.errors.push(e);
+      }
+    }
+
+    // starts on a child of the node on top of the frames, one level deeper. the frame
+    // leaves the level again when it takes the child
+    fn nested(&mut self, goal: Goal) -> Step {
+      let over = self.level >= self.max_level;
+      self.level += 1;
+      match self.tokens.peek().cloned() {
+        Some(token) if over => Step::Done(self.too_deep(token)),
+        _ => Step::Parse(goal),
+      }
+    }
+
+    // reports `token` as going past the limit and skips the rest of the input, as going on
+    // would nest just as deeply
+    fn too_deep(&mut self, token: Token) -> Ast {
+      let start = token.loc.0;
+      self.error(ParseError::TooDeep(token, self.max_level));
+      self.gave_up = true;
+      while self.next().is_some() {}
+      Ast::error(Loc(start, self.end))
+    }
+
+    // a recursive descent with the calls on a stack of frames rather than the native one,
+    // so input of any depth within `max_level` is parsed however small that is
+    fn parse_expr(&mut self) -> Ast {
+      self.parse(Goal::Expr)
+    }
+
+    fn parse(&mut self, goal: Goal) -> Ast {
+      let mut frames = Vec::new();
+      let mut step = Step::Parse(goal);
+      loop {
+        step = match step {
+          Step::Parse(goal) => self.start(goal, &mut frames),
+          Step::Done(e) => match frames.pop() {
+            Some(frame) => self.resume(frame, e, &mut frames),
+            None => return e,
+          },
         };
-        let e = parse_atom(tokens)?;
-        let loc = op.loc.merge(&e.loc);
-        Ok(Ast::uniop(op, e, loc))
       }
-      _ => parse_atom(tokens),
     }
-  }
 
-  fn parse_atom<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Ast, ParseError>
-  where
-    Tokens: Iterator<Item = Token>,
-  {
-    tokens
-      .next()
-      .ok_or(ParseError::Eof)
-      .and_then(|token| match token.value {
-        TokenKind::Number(n) => Ok(Ast::new(AstKind::Num(n), token.loc)),
-        TokenKind::LParen => {
-          let e = parse_expr(tokens)?;
-          match tokens.next() {
+    fn start(&mut self, goal: Goal, frames: &mut Vec<Frame>) -> Step {
+      match goal {
+        Goal::Expr => {
+          frames.push(Frame::Expr);
+          Step::Parse(Goal::Bp(0))
+        }
+        Goal::Equation => {
+          frames.push(Frame::Equation);
+          Step::Parse(Goal::Bp(0))
+        }
+        Goal::Bp(min_bp) => {
+          let start = self.tokens.peek().map_or(self.end, |token| token.loc.0);
+          frames.push(Frame::Bp { min_bp, start });
+          let table = self.table;
+          let prefix = self
+            .tokens
+            .peek()
+            .and_then(|token| table.prefix(&token.value))
+            .cloned();
+          match prefix {
+            Some(op) => {
+              let op_loc = self.next().unwrap().loc;
+              let bp = op.bp;
+              frames.push(Frame::Prefix { op, op_loc });
+              self.nested(Goal::Bp(bp))
+            }
+            None => self.parse_atom(frames),
+          }
+        }
+      }
+    }
+
+    // hands `e` to the frame which was waiting for it
+    fn resume(&mut self, frame: Frame, e: Ast, frames: &mut Vec<Frame>) -> Step {
+      match frame {
+        Frame::Expr => match self.peek_kind() {
+          Some(&TokenKind::Equal) => {
+            let eq = self.next().unwrap();
+            let name = match e.value {
+              AstKind::Var(ref name) => Some(name.clone()),
+              _ => {
+                self.error(ParseError::NotAssignable(eq));
+                None
+              }
+            };
+            // assignment is right associative: `a = b = 1` sets both
+            frames.push(Frame::Assign { name, loc: e.loc });
+            self.nested(Goal::Expr)
+          }
+          _ => Step::Done(e),
+        },
+        Frame::Assign { name, loc } => {
+          self.level -= 1;
+          let loc = loc.merge(&e.loc);
+          Step::Done(match name {
+            Some(name) => Ast::assign(name, e, loc),
+            None => Ast::error(loc),
+          })
+        }
+        Frame::Equation => match self.peek_kind() {
+          Some(&TokenKind::Equal) => {
+            let eq = self.next().unwrap().loc;
+            frames.push(Frame::Rhs { l: e, eq });
+            self.nested(Goal::Bp(0))
+          }
+          _ => Step::Done(e),
+        },
+        Frame::Rhs { l, eq } => {
+          self.level -= 1;
+          let loc = l.loc.merge(&e.loc);
+          Step::Done(Ast::binop(BinOp::sub(eq), l, e, loc))
+        }
+        Frame::Bp { min_bp, start } => {
+          let level = self.level;
+          self.parse_operators(e, min_bp, start, level, frames)
+        }
+        Frame::Prefix { op, op_loc } => {
+          self.level -= 1;
+          let loc = op_loc.merge(&e.loc);
+          Step::Done(apply(&op, op_loc, vec![e], loc))
+        }
+        Frame::Infix {
+          op,
+          op_loc,
+          l,
+          min_bp,
+          start,
+          level,
+        } => {
+          self.level -= 1;
+          let loc = l.loc.merge(&e.loc);
+          let e = apply(&op, op_loc, vec![l, e], loc);
+          self.parse_operators(e, min_bp, start, level, frames)
+        }
+        Frame::Paren(token) => {
+          self.level -= 1;
+          match self.next() {
             Some(Token {
               value: TokenKind::RParen,
               loc: _,
-            }) => Ok(e),
-            Some(t) => Err(ParseError::RedundantExpression(t)),
-            _ => Err(ParseError::UnclosedOpenParen(token)),
+            }) => {}
+            Some(t) => {
+              self.error(ParseError::RedundantExpression(t));
+              self.skip_arg();
+              if self.next().is_none() {
+                self.error(ParseError::UnclosedOpenParen(token));
+              }
+            }
+            _ => self.error(ParseError::UnclosedOpenParen(token)),
+          }
+          self.depth -= 1;
+          Step::Done(e)
+        }
+        Frame::Args {
+          name,
+          loc,
+          lparen,
+          mut args,
+        } => {
+          self.level -= 1;
+          args.push(e);
+          let end = loop {
+            match self.next() {
+              Some(Token {
+                value: TokenKind::Comma,
+                ..
+              }) => {
+                frames.push(Frame::Args {
+                  name,
+                  loc,
+                  lparen,
+                  args,
+                });
+                return self.nested(Goal::Expr);
+              }
+              Some(Token {
+                value: TokenKind::RParen,
+                loc: end,
+              }) => break end,
+              Some(t) => {
+                self.error(ParseError::UnexpectedToken(t));
+                self.skip_arg();
+              }
+              None => {
+                self.error(ParseError::UnclosedOpenParen(lparen));
+                break Loc(self.end, self.end);
+              }
+            }
+          };
+          self.depth -= 1;
+          Step::Done(Ast::call(&name, args, loc.merge(&end)))
+        }
+      }
+    }
+
+    // the operators of a Pratt parser after its first operand `e`, as long as they bind
+    // tighter than `min_bp`. a chain of infix operators such as `1 + 2 + 3` nests no
+    // deeper than its first operand, but each of a chain of postfix ones takes the call
+    // before it as its argument, so it nests a level deeper than `level` where they start.
+    // `e` starts at `start` in the source, or just inside a parenthesis that does
+    fn parse_operators(
+      &mut self,
+      mut e: Ast,
+      min_bp: u32,
+      start: usize,
+      level: usize,
+      frames: &mut Vec<Frame>,
+    ) -> Step {
+      let table = self.table;
+      loop {
+        let op = match self.peek_kind().and_then(|t| table.infix_or_postfix(t)) {
+          Some(op) if op.bp > min_bp => op.clone(),
+          _ => {
+            self.level = level;
+            return Step::Done(e);
+          }
+        };
+        match op.fixity {
+          Fixity::Infix(assoc) => {
+            let op_loc = self.next().unwrap().loc;
+            // a right associative operator lets an operator of its own power take the
+            // right operand, so `2^3^2` is `2^(3^2)`
+            let r_bp = match assoc {
+              Assoc::Left => op.bp,
+              Assoc::Right => op.bp.saturating_sub(1),
+            };
+            frames.push(Frame::Infix {
+              op,
+              op_loc,
+              l: e,
+              min_bp,
+              start,
+              level,
+            });
+            return self.nested(Goal::Bp(r_bp));
+          }
+          Fixity::Postfix => {
+            if self.level >= self.max_level {
+              let token = self.tokens.peek().cloned().unwrap();
+              self.level = level;
+              return Step::Done(self.too_deep(token));
+            }
+            let op_loc = self.next().unwrap().loc;
+            // the operand of `(1/2)!` is the parenthesis, not the `1/2` in it
+            let loc = Loc(start, start).merge(&e.loc).merge(&op_loc);
+            e = apply(&op, op_loc, vec![e], loc);
+            self.level += 1;
           }
+          Fixity::Prefix => unreachable!(),
         }
-        _ => Err(ParseError::NotExpression(token)),
+      }
+    }
+
+    fn parse_atom(&mut self, frames: &mut Vec<Frame>) -> Step {
+      if self.depth > 0 {
+        if let Some(&TokenKind::RParen) | Some(&TokenKind::Comma) = self.peek_kind() {
+          let token = self.tokens.peek().unwrap().clone();
+          let loc = token.loc.clone();
+          self.error(ParseError::NotExpression(token));
+          return Step::Done(Ast::error(Loc(loc.0, loc.0)));
+        }
+      }
+      let token = match self.next() {
+        Some(token) => token,
+        None => {
+          self.error(ParseError::Eof);
+          return Step::Done(Ast::error(Loc(self.end, self.end)));
+        }
+      };
+      match token.value {
+        TokenKind::Number(n) => Step::Done(Ast::new(AstKind::Num(n), token.loc)),
+        TokenKind::Float(x) => Step::Done(Ast::new(AstKind::Float(x), token.loc)),
+        TokenKind::Ident(ref name) => match self.peek_kind() {
+          Some(&TokenKind::LParen) => self.parse_call(name, token.loc.clone(), frames),
+          _ => Step::Done(Ast::var(name, token.loc)),
+        },
+        TokenKind::LParen => {
+          self.depth += 1;
+          frames.push(Frame::Paren(token));
+          self.nested(Goal::Expr)
+        }
+        _ => {
+          let loc = token.loc.clone();
+          self.error(ParseError::NotExpression(token));
+          Step::Done(Ast::error(loc))
+        }
+      }
+    }
+
+    // `name(arg, ...)`, called with the `(` as the next token. the first argument of
+    // `solve` is an equation
+    fn parse_call(&mut self, name: &str, loc: Loc, frames: &mut Vec<Frame>) -> Step {
+      let lparen = self.next().unwrap();
+      if let Some(&TokenKind::RParen) = self.peek_kind() {
+        let rparen = self.next().unwrap();
+        return Step::Done(Ast::call(name, Vec::new(), loc.merge(&rparen.loc)));
+      }
+      self.depth += 1;
+      frames.push(Frame::Args {
+        name: name.to_string(),
+        loc,
+        lparen,
+        args: Vec::new(),
+      });
+      self.nested(match name {
+        "solve" => Goal::Equation,
+        _ => Goal::Expr,
       })
+    }
+
+    // skips to the `,` or `)` ending the current argument, over nested parentheses
+    fn skip_arg(&mut self) {
+      let mut depth = 0;
+      while let Some(kind) = self.peek_kind() {
+        match kind {
+          TokenKind::Comma | TokenKind::RParen if depth == 0 => return,
+          TokenKind::LParen => depth += 1,
+          TokenKind::RParen => depth -= 1,
+          _ => {}
+        }
+        self.next();
+      }
+    }
+  }
+
+  // builds the node for `op` at `op_loc` over its operands
+  fn apply(op: &Operator, op_loc: Loc, mut operands: Vec<Ast>, loc: Loc) -> Ast {
+    match op.action {
+      Action::UniOp(ref kind) => {
+        let e = operands.pop().unwrap();
+        Ast::uniop(UniOp::new(kind.clone(), op_loc), e, loc)
+      }
+      Action::BinOp(ref kind) => {
+        let r = operands.pop().unwrap();
+        let l = operands.pop().unwrap();
+        Ast::binop(BinOp::new(kind.clone(), op_loc), l, r, loc)
+      }
+      Action::Call(ref name) => Ast::call(name, operands, loc),
+    }
   }
 
   #[test]
@@ -603,95 +1954,1516 @@ pub mod ast {
         ),
         Loc(0, 15)
       ))
-    )
+    );
+
+    assert_eq!(
+      parse(lex("x = y = 2").unwrap()),
+      Ok(Ast::assign(
+        "x".to_string(),
+        Ast::assign("y".to_string(), Ast::num(2, Loc(8, 9)), Loc(4, 9)),
+        Loc(0, 9)
+      ))
+    );
+    assert_eq!(
+      parse(lex("x + 1 = 2").unwrap()),
+      Err(ParseError::NotAssignable(Token::new(
+        TokenKind::Equal,
+        Loc(6, 7)
+      )))
+    );
+    assert_eq!(
+      parse(lex("max(1, f())").unwrap()),
+      Ok(Ast::call(
+        "max",
+        vec![Ast::num(1, Loc(4, 5)), Ast::call("f", vec![], Loc(7, 10))],
+        Loc(0, 11)
+      ))
+    );
+    assert_eq!(
+      parse(lex("sin(1 2)").unwrap()),
+      Err(ParseError::UnexpectedToken(Token::new(
+        TokenKind::Number(BigInt::from(2u64)),
+        Loc(6, 7)
+      )))
+    );
+    assert_eq!(parse(lex("sin(1,").unwrap()), Err(ParseError::Eof));
+    assert_eq!(
+      parse(lex("-x").unwrap()),
+      Ok(Ast::uniop(
+        UniOp::minus(Loc(0, 1)),
+        Ast::var("x", Loc(1, 2)),
+        Loc(0, 2)
+      ))
+    );
+
+    // `^` binds tighter than unary minus and groups from the right
+    assert_eq!(
+      parse(lex("-2^2").unwrap()),
+      Ok(Ast::uniop(
+        UniOp::minus(Loc(0, 1)),
+        Ast::binop(
+          BinOp::pow(Loc(2, 3)),
+          Ast::num(2, Loc(1, 2)),
+          Ast::num(2, Loc(3, 4)),
+          Loc(1, 4)
+        ),
+        Loc(0, 4)
+      ))
+    );
+    assert_eq!(
+      parse(lex("2^3**2").unwrap()),
+      Ok(Ast::binop(
+        BinOp::pow(Loc(1, 2)),
+        Ast::num(2, Loc(0, 1)),
+        Ast::binop(
+          BinOp::pow(Loc(3, 5)),
+          Ast::num(3, Loc(2, 3)),
+          Ast::num(2, Loc(5, 6)),
+          Loc(2, 6)
+        ),
+        Loc(0, 6)
+      ))
+    );
+    assert_eq!(
+      parse(lex("2*x^-1").unwrap()),
+      Ok(Ast::binop(
+        BinOp::mult(Loc(1, 2)),
+        Ast::num(2, Loc(0, 1)),
+        Ast::binop(
+          BinOp::pow(Loc(3, 4)),
+          Ast::var("x", Loc(2, 3)),
+          Ast::uniop(UniOp::minus(Loc(4, 5)), Ast::num(1, Loc(5, 6)), Loc(4, 6)),
+          Loc(2, 6)
+        ),
+        Loc(0, 6)
+      ))
+    );
+    assert_eq!(
+      parse(lex("--1").unwrap()),
+      Ok(Ast::uniop(
+        UniOp::minus(Loc(0, 1)),
+        Ast::uniop(UniOp::minus(Loc(1, 2)), Ast::num(1, Loc(2, 3)), Loc(1, 3)),
+        Loc(0, 3)
+      ))
+    );
+    assert_eq!(parse(lex("2^").unwrap()), Err(ParseError::Eof));
+    // the factorial binds tighter than `-` and `^`
+    assert_eq!(
+      parse(lex("-2^3!").unwrap()),
+      Ok(Ast::uniop(
+        UniOp::minus(Loc(0, 1)),
+        Ast::binop(
+          BinOp::pow(Loc(2, 3)),
+          Ast::num(2, Loc(1, 2)),
+          Ast::call("fact", vec![Ast::num(3, Loc(3, 4))], Loc(3, 5)),
+          Loc(1, 5)
+        ),
+        Loc(0, 5)
+      ))
+    );
+
+    // operators registered at run time lex as symbols and parse like the built-in ones
+    use super::lexer::lex_with;
+    use super::operator::{Action, Assoc, Operator, OperatorTable};
+    let mut table = OperatorTable::default();
+    table.register(Operator::postfix(
+      "%",
+      50,
+      Action::Call("percent".to_string()),
+    ));
+    table.register(Operator::infix(
+      "<>",
+      5,
+      Assoc::Left,
+      Action::Call("max".to_string()),
+    ));
+    assert_eq!(
+      parse_with(lex_with("1 + 2 <> -3%", &table).unwrap(), &table),
+      Ok(Ast::call(
+        "max",
+        vec![
+          Ast::binop(
+            BinOp::add(Loc(2, 3)),
+            Ast::num(1, Loc(0, 1)),
+            Ast::num(2, Loc(4, 5)),
+            Loc(0, 5)
+          ),
+          Ast::uniop(
+            UniOp::minus(Loc(9, 10)),
+            Ast::call("percent", vec![Ast::num(3, Loc(10, 11))], Loc(10, 12)),
+            Loc(9, 12)
+          ),
+        ],
+        Loc(0, 12)
+      ))
+    );
+    assert_eq!(
+      parse_with(lex_with("2 %", &table).unwrap(), &table),
+      Ok(Ast::call(
+        "percent",
+        vec![Ast::num(2, Loc(0, 1))],
+        Loc(0, 3)
+      ))
+    );
+    assert!(lex("1 <> 2").is_err());
+
+    // the parser goes on after an error and marks where it was in the tree
+    let table = OperatorTable::default();
+    let (ast, errors) = parse_all(lex("(1 + ) * max(2 3, ) ^").unwrap(), &table);
+    assert_eq!(
+      errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+      vec![
+        "5-6: ')' is not a start of expression",
+        "15-16: '3' is not expected",
+        "18-19: ')' is not a start of expression",
+        "End of file",
+      ]
+    );
+    assert_eq!(
+      ast,
+      Ast::binop(
+        BinOp::mult(Loc(7, 8)),
+        Ast::binop(
+          BinOp::add(Loc(3, 4)),
+          Ast::num(1, Loc(1, 2)),
+          Ast::error(Loc(5, 5)),
+          Loc(1, 5)
+        ),
+        Ast::binop(
+          BinOp::pow(Loc(20, 21)),
+          Ast::call(
+            "max",
+            vec![Ast::num(2, Loc(13, 14)), Ast::error(Loc(18, 18))],
+            Loc(9, 19)
+          ),
+          Ast::error(Loc(21, 21)),
+          Loc(9, 21)
+        ),
+        Loc(1, 21)
+      )
+    );
+    let (ast, errors) = parse_all(lex("x + 1 = (2").unwrap(), &table);
+    assert_eq!(ast, Ast::error(Loc(0, 10)));
+    assert_eq!(
+      errors,
+      vec![
+        ParseError::NotAssignable(Token::new(TokenKind::Equal, Loc(6, 7))),
+        ParseError::UnclosedOpenParen(Token::new(TokenKind::LParen, Loc(8, 9))),
+      ]
+    );
+    let (_, errors) = parse_all(lex("1 + 2) 3 *").unwrap(), &table);
+    assert_eq!(
+      errors,
+      vec![
+        ParseError::RedundantExpression(Token::new(TokenKind::RParen, Loc(5, 6))),
+        ParseError::Eof,
+      ]
+    );
+
+    // printing keeps the parentheses the tree needs and drops the others
+    for (input, printed) in [
+      ("((1 + 2)) * 3 - (4 - 5)", "(1 + 2) * 3 - (4 - 5)"),
+      ("(2 ^ 3) ^ 4 + 2 ^ (3 ^ 4)", "(2 ^ 3) ^ 4 + 2 ^ 3 ^ 4"),
+      ("x = -(a + b) / max(1, +2.5)", "x = -(a + b) / max(1, +2.5)"),
+      ("-2 ** 2", "-2 ^ 2"),
+    ] {
+      assert_eq!(parse(lex(input).unwrap()).unwrap().to_string(), printed);
+    }
+    let negative = Ast::new(AstKind::Num(BigInt::from(-2i64)), Loc(0, 2));
+    let square = Ast::binop(
+      BinOp::pow(Loc(3, 4)),
+      negative,
+      Ast::num(2, Loc(5, 6)),
+      Loc(0, 6),
+    );
+    assert_eq!(square.to_string(), "(-2) ^ 2");
   }
 }
 
 pub mod interpreter {
   use super::ast::*;
-  use super::Annot;
+  use super::builtins;
+  use super::{Annot, Loc};
+  use crate::i18n::{tr, tr_with};
+  use std::fmt;
 
-  pub struct Interpreter;
-  impl Interpreter {
-    pub fn new() -> Self {
-      Interpreter
-    }
+  pub use super::number::{Number, Value};
 
-    pub fn eval(&mut self, expr: &Ast) -> Result<i64, InterpreterError> {
-      match expr.value {
-        AstKind::Num(n) => Ok(n as i64),
-        AstKind::UniOp { ref op, ref e } => {
-          let e = self.eval(e)?;
-          Ok(self.eval_uniop(op, e))
-        }
-        AstKind::BinOp {
-          ref op,
-          ref l,
-          ref r,
-        } => {
-          let l = self.eval(l)?;
-          let r = self.eval(r)?;
-          self
-            .eval_binop(op, l, r)
-            .map_err(|e| InterpreterError::new(e, expr.loc.clone()))
-        }
+  // the backends the REPL can switch between
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+  pub enum Mode {
+    // 64-bit integers promoted to floats when mixed with them
+    Machine,
+    // 64-bit integers only
+    Int,
+    // floats only
+    Float,
+    // arbitrary-precision integers which never overflow
+    Bignum,
+    // exact fractions, so division never truncates
+    Rational,
+    // complex numbers of floats
+    Complex,
+  }
+
+  impl Mode {
+    pub fn interpreter(self) -> Box<dyn Evaluator> {
+      use super::bignum::BigInt;
+      use super::complex::Complex;
+      use super::rational::Rational;
+
+      match self {
+        Mode::Machine => Box::new(Interpreter::<Value>::new()),
+        Mode::Int => Box::new(Interpreter::<i64>::new()),
+        Mode::Float => Box::new(Interpreter::<f64>::new()),
+        Mode::Bignum => Box::new(Interpreter::<BigInt>::new()),
+        Mode::Rational => Box::new(Interpreter::<Rational>::new()),
+        Mode::Complex => Box::new(Interpreter::<Complex>::new()),
       }
     }
+  }
 
-    pub fn eval_uniop(&mut self, op: &UniOp, n: i64) -> i64 {
-      match op.value {
-        UniOpKind::Plus => n,
-        UniOpKind::Minus => -n,
+  impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+        Mode::Machine => write!(f, "machine"),
+        Mode::Int => write!(f, "int"),
+        Mode::Float => write!(f, "float"),
+        Mode::Bignum => write!(f, "bignum"),
+        Mode::Rational => write!(f, "rational"),
+        Mode::Complex => write!(f, "complex"),
       }
     }
+  }
 
-    pub fn eval_binop(&mut self, op: &BinOp, l: i64, r: i64) -> Result<i64, InterpreterErrorKind> {
-      match op.value {
-        BinOpKind::Add => Ok(l + r),
-        BinOpKind::Sub => Ok(l - r),
-        BinOpKind::Mult => Ok(l * r),
-        BinOpKind::Div => {
-          if r == 0 {
-            Err(InterpreterErrorKind::DivisionByZero)
-          } else {
-            Ok(l / r)
-          }
-        }
+  use std::str::FromStr;
+  impl FromStr for Mode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+      match s {
+        "machine" => Ok(Mode::Machine),
+        "int" => Ok(Mode::Int),
+        "float" => Ok(Mode::Float),
+        "bignum" => Ok(Mode::Bignum),
+        "rational" => Ok(Mode::Rational),
+        "complex" => Ok(Mode::Complex),
+        _ => Err(tr_with("repl.unknown_mode", &[&s])),
       }
     }
   }
 
-  #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-  pub enum InterpreterErrorKind {
-    DivisionByZero,
+  use super::context::Context;
+  use super::limits::{Limit, Limits};
+  use std::collections::HashMap;
+  // variables live as long as the interpreter, so they carry over between REPL lines
+  pub struct Interpreter<N = Value> {
+    env: HashMap<String, N>,
+    context: Context<N>,
+    // the reductions of the last evaluation, when they are recorded
+    trace: Option<Vec<Step<N>>>,
+    limits: Limits,
+    // how many nodes the evaluation has reduced so far
+    steps: usize,
+    // how deeply the expression evaluated now nests in the whole input, which is more
+    // than none in the equation of a `solve` call
+    depth: usize,
   }
-  pub type InterpreterError = Annot<InterpreterErrorKind>;
-
-  use std::fmt;
-  impl fmt::Display for InterpreterError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-      use self::InterpreterErrorKind::*;
+  impl<N: Number> Default for Interpreter<N> {
+    fn default() -> Self {
+      Self::new()
+    }
+  }
+  impl<N: Number> Interpreter<N> {
+    pub fn new() -> Self {
+      Self::with_context(Context::new())
+    }
 
-      match self.value {
-        DivisionByZero => write!(f, "zero division error"),
+    // an interpreter which also knows the names the host program defines
+    pub fn with_context(context: Context<N>) -> Self {
+      Interpreter {
+        env: HashMap::new(),
+        context,
+        trace: None,
+        limits: Limits::default(),
+        steps: 0,
+        depth: 0,
       }
     }
-  }
-}
 
-#[cfg(test)]
-mod test {
-  use super::ast::parse;
-  use super::interpreter::Interpreter;
-  use super::lexer::lex;
+    // the bounds on each evaluation, for input which cannot be trusted to be reasonable
+    pub fn set_limits(&mut self, limits: Limits) {
+      self.limits = limits;
+    }
 
-  #[test]
-  fn test_interpreter() {
-    let tokens = lex("1 + 2 * 3 - 10").unwrap();
-    let ast = parse(tokens).unwrap();
-    let mut interpreter = Interpreter::new();
+    pub fn limits(&self) -> &Limits {
+      &self.limits
+    }
+
+    pub fn context_mut(&mut self) -> &mut Context<N> {
+      &mut self.context
+    }
+
+    // whether `eval` records every sub-expression it reduces to a value
+    pub fn set_trace(&mut self, on: bool) {
+      self.trace = if on { Some(Vec::new()) } else { None };
+    }
+
+    pub fn tracing(&self) -> bool {
+      self.trace.is_some()
+    }
+
+    // the reductions of the last `eval` in the order they were made, up to an error if there
+    // was one. a literal is a value already, so it has none
+    pub fn trace(&self) -> &[Step<N>] {
+      self.trace.as_deref().unwrap_or(&[])
+    }
+
+    // evaluates one whole input and remembers the result as `ans`
+    pub fn eval(&mut self, expr: &Ast) -> Result<N, InterpreterError> {
+      self.clear_trace();
+      self.restart();
+      let n = self.eval_ast(expr)?;
+      self.store("ans", n.clone());
+      Ok(n)
+    }
+
+    // walks the tree with a stack of its own rather than recursing, so a tree of any depth
+    // within the limits is evaluated however small the native stack is. the operands of a
+    // node are evaluated from left to right before it
+    pub(super) fn eval_ast(&mut self, expr: &Ast) -> Result<N, InterpreterError> {
+      // the nodes whose operands are being evaluated, with their depths and the values of
+      // the operands done
+      let mut pending: Vec<(&Ast, usize, Vec<N>)> = Vec::new();
+      let (mut node, mut depth) = (expr, 0);
+      loop {
+        let fail = |e| InterpreterError::new(e, node.loc.clone());
+        self.enter(depth).map_err(fail)?;
+        if let AstKind::Assign { ref name, .. } = node.value {
+          self.assignable(name).map_err(fail)?;
+        }
+        let mut n = match node.value.child(0) {
+          // its arguments are not values to compute first
+          _ if self.solves(node) => {
+            let n = self.solve_call(node, depth)?;
+            self.traced(node, n)?
+          }
+          Some(first) => {
+            pending.push((node, depth, Vec::new()));
+            depth += nesting(node, 0);
+            node = first;
+            continue;
+          }
+          None => self.reduced(node, Vec::new())?,
+        };
+        // back up with the value, through the nodes it was the last operand of
+        loop {
+          let (parent, parent_depth, values) = match pending.last_mut() {
+            Some((parent, depth, values)) => (*parent, *depth, values),
+            None => return Ok(n),
+          };
+          values.push(n);
+          if let Some(next) = parent.value.child(values.len()) {
+            depth = parent_depth + nesting(parent, values.len());
+            node = next;
+            break;
+          }
+          let values = pending.pop().unwrap().2;
+          n = self.reduced(parent, values)?;
+        }
+      }
+    }
+
+    // the value of `expr` from those of its operands, checked against the limits and
+    // recorded in the trace
+    fn reduced(&mut self, expr: &Ast, operands: Vec<N>) -> Result<N, InterpreterError> {
+      let n = self
+        .reduce(expr, operands)
+        .map_err(|e| InterpreterError::new(e, expr.loc.clone()))?;
+      self.traced(expr, n)
+    }
+
+    // the value of `expr`, checked against the limits and recorded in the trace
+    pub(super) fn traced(&mut self, expr: &Ast, n: N) -> Result<N, InterpreterError> {
+      self
+        .fits(&n)
+        .map_err(|e| InterpreterError::new(e, expr.loc.clone()))?;
+      match expr.value {
+        AstKind::Num(_) | AstKind::Float(_) => {}
+        _ => self.record(&n, &expr.loc),
+      }
+      Ok(n)
+    }
+
+    // adds a reduction to the trace, if one is being recorded
+    pub(super) fn record(&mut self, n: &N, loc: &Loc) {
+      if let Some(ref mut trace) = self.trace {
+        trace.push(Step::new(n.clone(), loc.clone()));
+      }
+    }
+
+    // empties the trace for a new evaluation
+    pub(super) fn clear_trace(&mut self) {
+      if let Some(ref mut trace) = self.trace {
+        trace.clear();
+      }
+    }
+
+    // starts counting the steps of an evaluation from none
+    pub(super) fn restart(&mut self) {
+      self.steps = 0;
+    }
+
+    // counts the step of evaluating a node `depth` levels below the expression evaluated,
+    // failing past the limits
+    pub(super) fn enter(&mut self, depth: usize) -> Result<(), InterpreterErrorKind> {
+      self.step()?;
+      if self.depth + depth >= self.limits.depth {
+        return Err(InterpreterErrorKind::LimitExceeded(Limit::Depth(
+          self.limits.depth,
+        )));
+      }
+      Ok(())
+    }
+
+    // counts one step, failing past the limit
+    fn step(&mut self) -> Result<(), InterpreterErrorKind> {
+      self.steps += 1;
+      if self.steps > self.limits.steps {
+        return Err(InterpreterErrorKind::LimitExceeded(Limit::Steps(
+          self.limits.steps,
+        )));
+      }
+      Ok(())
+    }
+
+    // fails on a value larger than the limit
+    pub(super) fn fits(&self, n: &N) -> Result<(), InterpreterErrorKind> {
+      if n.bits() > self.limits.bits {
+        return Err(InterpreterErrorKind::LimitExceeded(Limit::Bits(
+          self.limits.bits,
+        )));
+      }
+      Ok(())
+    }
+
+    // a power is refused before it is computed, as computing it is what takes too long
+    fn power_fits(&self, base: &N, exp: &N) -> Result<(), InterpreterErrorKind> {
+      self.grows_within(base.pow_bits(exp))
+    }
+
+    // and so is a factorial
+    fn factorial_fits(&self, n: &N) -> Result<(), InterpreterErrorKind> {
+      self.grows_within(n.fact_bits())
+    }
+
+    fn grows_within(&self, bits: u64) -> Result<(), InterpreterErrorKind> {
+      if bits > self.limits.bits {
+        return Err(InterpreterErrorKind::LimitExceeded(Limit::Bits(
+          self.limits.bits,
+        )));
+      }
+      Ok(())
+    }
+
+    // runs `f` without recording its evaluations in the trace, as `solve` makes many
+    pub(super) fn untraced<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+      let trace = self.trace.take();
+      let t = f(self);
+      self.trace = trace;
+      t
+    }
+
+    // runs `f` evaluating expressions as though they nested `levels` deeper than now
+    pub(super) fn deeper<T>(&mut self, levels: usize, f: impl FnOnce(&mut Self) -> T) -> T {
+      self.depth += levels;
+      let t = f(self);
+      self.depth -= levels;
+      t
+    }
+
+    // whether a node is a call of `solve`, unless the host defines a function of its own
+    // by that name
+    pub(super) fn solves(&self, expr: &Ast) -> bool {
+      match expr.value {
+        AstKind::Call { ref name, .. } => name == "solve" && !self.defines_function(name),
+        _ => false,
+      }
+    }
+
+    fn reduce(&mut self, expr: &Ast, mut operands: Vec<N>) -> Result<N, InterpreterErrorKind> {
+      match expr.value {
+        AstKind::Num(ref n) => N::from_integer(n),
+        AstKind::Float(x) => N::from_float(x),
+        AstKind::Var(ref name) => self.load(name),
+        AstKind::Assign { ref name, .. } => {
+          let n = operands.pop().unwrap();
+          self.store(name, n.clone());
+          Ok(n)
+        }
+        AstKind::Call { ref name, .. } => self.call(name, &operands),
+        // only a tree from `parse_all` with errors has these
+        AstKind::Error => Err(InterpreterErrorKind::SyntaxError),
+        AstKind::UniOp { ref op, .. } => {
+          let n = operands.pop().unwrap();
+          self.eval_uniop(op, n)
+        }
+        AstKind::BinOp { ref op, .. } => {
+          let r = operands.pop().unwrap();
+          let l = operands.pop().unwrap();
+          self.eval_binop(op, l, r)
+        }
+      }
+    }
+
+    pub fn eval_uniop(&mut self, op: &UniOp, n: N) -> Result<N, InterpreterErrorKind> {
+      op.value.apply(n)
+    }
+
+    pub fn eval_binop(&mut self, op: &BinOp, l: N, r: N) -> Result<N, InterpreterErrorKind> {
+      self.apply_binop(&op.value, l, r)
+    }
+
+    // an operator within the limits, shared with the `vm`
+    pub(super) fn apply_binop(
+      &self,
+      op: &BinOpKind,
+      l: N,
+      r: N,
+    ) -> Result<N, InterpreterErrorKind> {
+      if *op == BinOpKind::Pow {
+        self.power_fits(&l, &r)?;
+      }
+      op.apply(l, r)
+    }
+
+    // the names, shared with the `vm` so both resolve them the same way: the host's
+    // first, then the variables, which shadow the constants such as `pi`
+    pub(super) fn load(&self, name: &str) -> Result<N, InterpreterErrorKind> {
+      match self
+        .context
+        .value(name)
+        .or_else(|| self.env.get(name).cloned())
+      {
+        Some(n) => Ok(n),
+        None => N::constant(name)
+          .unwrap_or_else(|| Err(InterpreterErrorKind::UndefinedVariable(name.to_string()))),
+      }
+    }
+
+    pub(super) fn assignable(&self, name: &str) -> Result<(), InterpreterErrorKind> {
+      if self.context.is_defined(name) {
+        Err(InterpreterErrorKind::ReadOnly(name.to_string()))
+      } else {
+        Ok(())
+      }
+    }
+
+    // overwrites in place when it can, as `ans` is stored after every evaluation
+    pub(super) fn store(&mut self, name: &str, n: N) {
+      match self.env.get_mut(name) {
+        Some(value) => *value = n,
+        None => {
+          self.env.insert(name.to_string(), n);
+        }
+      }
+    }
+
+    // sets or removes a variable, giving back what it was, for `solve` to put it back
+    pub(super) fn replace(&mut self, name: &str, n: Option<N>) -> Option<N> {
+      match n {
+        Some(n) => self.env.insert(name.to_string(), n),
+        None => self.env.remove(name),
+      }
+    }
+
+    pub(super) fn call(&self, name: &str, args: &[N]) -> Result<N, InterpreterErrorKind> {
+      self
+        .context
+        .call(name, args)
+        .unwrap_or_else(|| match name.parse() {
+          Ok(f) => self.builtin(f, args),
+          Err(()) => builtins::call(name, args),
+        })
+    }
+
+    // whether a call of the name goes to the host rather than a built-in function
+    pub(super) fn defines_function(&self, name: &str) -> bool {
+      self.context.has_function(name)
+    }
+
+    // `call` with the built-in function of the name looked up in advance
+    pub(super) fn call_builtin(
+      &self,
+      f: builtins::Func,
+      args: &[N],
+    ) -> Result<N, InterpreterErrorKind> {
+      self
+        .context
+        .call(f.name(), args)
+        .unwrap_or_else(|| self.builtin(f, args))
+    }
+
+    // `pow` refused as `^` is when too large, and `fact` likewise
+    fn builtin(&self, f: builtins::Func, args: &[N]) -> Result<N, InterpreterErrorKind> {
+      match (f, args) {
+        (builtins::Func::Pow, [base, exp]) => self.power_fits(base, exp)?,
+        (builtins::Func::Fact, [n]) => self.factorial_fits(n)?,
+        _ => {}
+      }
+      builtins::call_func(f, args)
+    }
+  }
+
+  // how much deeper than a node its `i`th operand nests, which for the left operand of an
+  // infix operator is none, as the parser takes a chain such as `1 + 2 + 3` to be flat
+  pub(super) fn nesting(expr: &Ast, i: usize) -> usize {
+    match expr.value {
+      AstKind::BinOp { .. } if i == 0 => 0,
+      _ => 1,
+    }
+  }
+
+  impl UniOpKind {
+    pub fn apply<N: Number>(&self, n: N) -> Result<N, InterpreterErrorKind> {
+      match self {
+        UniOpKind::Plus => Ok(n),
+        UniOpKind::Minus => n.neg(),
+      }
+    }
+  }
+
+  impl BinOpKind {
+    pub fn apply<N: Number>(&self, l: N, r: N) -> Result<N, InterpreterErrorKind> {
+      match self {
+        BinOpKind::Add => l.add(&r),
+        BinOpKind::Sub => l.sub(&r),
+        BinOpKind::Mult => l.mul(&r),
+        BinOpKind::Div => l.div(&r),
+        BinOpKind::Pow => l.pow(&r),
+      }
+    }
+  }
+
+  // an `Interpreter` with its number type erased, so the REPL can switch backends at run time
+  pub trait Evaluator {
+    fn name(&self) -> &'static str;
+    fn eval_to_string(&mut self, expr: &Ast) -> Result<String, InterpreterError>;
+    fn simplify(&self, expr: &Ast) -> Ast;
+    fn diff(&self, expr: &Ast, x: &str) -> Result<Ast, InterpreterError>;
+    fn set_trace(&mut self, on: bool);
+    fn limits(&self) -> Limits;
+    fn set_limits(&mut self, limits: Limits);
+    fn trace_to_strings(&self) -> Vec<Step<String>>;
+    // `start` is a guess or the ends of an interval
+    fn solve(&mut self, f: &Ast, x: &str, start: &[Ast]) -> Result<String, InterpreterError>;
+  }
+
+  impl<N: Number> Evaluator for Interpreter<N> {
+    fn name(&self) -> &'static str {
+      N::NAME
+    }
+    fn eval_to_string(&mut self, expr: &Ast) -> Result<String, InterpreterError> {
+      self.eval(expr).map(|n| n.describe())
+    }
+    fn simplify(&self, expr: &Ast) -> Ast {
+      Interpreter::simplify(self, expr)
+    }
+    fn diff(&self, expr: &Ast, x: &str) -> Result<Ast, InterpreterError> {
+      Interpreter::diff(self, expr, x)
+    }
+    fn set_trace(&mut self, on: bool) {
+      Interpreter::set_trace(self, on)
+    }
+    fn limits(&self) -> Limits {
+      *Interpreter::limits(self)
+    }
+    fn set_limits(&mut self, limits: Limits) {
+      Interpreter::set_limits(self, limits)
+    }
+    fn trace_to_strings(&self) -> Vec<Step<String>> {
+      self
+        .trace()
+        .iter()
+        .map(|step| Step::new(step.value.describe(), step.loc.clone()))
+        .collect()
+    }
+    fn solve(&mut self, f: &Ast, x: &str, start: &[Ast]) -> Result<String, InterpreterError> {
+      self.restart();
+      let start = self.start(start)?;
+      Interpreter::solve(self, f, x, start).map(|n| n.describe())
+    }
+  }
+
+  #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+  pub enum InterpreterErrorKind {
+    DivisionByZero,
+    Overflow,
+    Unsupported {
+      op: &'static str,
+      mode: &'static str,
+    },
+    Domain(String),
+    UndefinedVariable(String),
+    UnknownFunction(String),
+    ArityMismatch {
+      name: String,
+      expected: usize,
+      found: usize,
+    },
+    SyntaxError,
+    // an assignment to a name the host program defines
+    ReadOnly(String),
+    // a function or operator `diff` has no rule for, such as `floor`
+    NotDifferentiable(String),
+    // the ends of an interval `solve` was given, with no sign change between them
+    NoSignChange {
+      from: String,
+      to: String,
+    },
+    // where a search for a root started, when it found none
+    NoConvergence(String),
+    // the limit of an `Interpreter` an evaluation went past
+    LimitExceeded(Limit),
+    // what `solve` was given to solve for in place of a variable
+    NotVariable(String),
+  }
+  pub type InterpreterError = Annot<InterpreterErrorKind>;
+  // a sub-expression, by where it is, and the value it reduced to
+  pub type Step<N> = Annot<N>;
+
+  impl InterpreterErrorKind {
+    // the variant name, for the JSON output
+    pub fn kind(&self) -> &'static str {
+      use self::InterpreterErrorKind::*;
+      match self {
+        DivisionByZero => "DivisionByZero",
+        Overflow => "Overflow",
+        Unsupported { .. } => "Unsupported",
+        Domain(_) => "Domain",
+        UndefinedVariable(_) => "UndefinedVariable",
+        UnknownFunction(_) => "UnknownFunction",
+        ArityMismatch { .. } => "ArityMismatch",
+        SyntaxError => "SyntaxError",
+        ReadOnly(_) => "ReadOnly",
+        NotDifferentiable(_) => "NotDifferentiable",
+        NoSignChange { .. } => "NoSignChange",
+        NoConvergence(_) => "NoConvergence",
+        LimitExceeded(_) => "LimitExceeded",
+        NotVariable(_) => "NotVariable",
+      }
+    }
+
+    // the stable code, explained by `codes::explain`
+    pub fn code(&self) -> &'static str {
+      use self::InterpreterErrorKind::*;
+      match self {
+        DivisionByZero => "C0201",
+        Overflow => "C0202",
+        Unsupported { .. } => "C0203",
+        Domain(_) => "C0204",
+        UndefinedVariable(_) => "C0205",
+        UnknownFunction(_) => "C0206",
+        ArityMismatch { .. } => "C0207",
+        SyntaxError => "C0208",
+        ReadOnly(_) => "C0209",
+        NotDifferentiable(_) => "C0210",
+        NoSignChange { .. } => "C0211",
+        NoConvergence(_) => "C0212",
+        LimitExceeded(_) => "C0213",
+        NotVariable(_) => "C0214",
+      }
+    }
+  }
+  impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      use self::InterpreterErrorKind::*;
+
+      let message = match self.value {
+        DivisionByZero => tr("interp.division_by_zero").to_string(),
+        Overflow => tr("interp.overflow").to_string(),
+        Unsupported { op, mode } => tr_with("interp.unsupported", &[&op, &mode]),
+        Domain(ref reason) => tr_with("interp.domain", &[reason]),
+        UndefinedVariable(ref name) => tr_with("interp.undefined_variable", &[name]),
+        UnknownFunction(ref name) => tr_with("interp.unknown_function", &[name]),
+        ArityMismatch {
+          ref name,
+          expected,
+          found,
+        } => tr_with(
+          "interp.arity",
+          &[
+            name,
+            &expected,
+            &tr(super::arguments(expected)),
+            &found,
+            &tr(if found == 1 { "word.was" } else { "word.were" }),
+          ],
+        ),
+        SyntaxError => tr("interp.syntax_error").to_string(),
+        ReadOnly(ref name) => tr_with("interp.read_only", &[name]),
+        NotDifferentiable(ref name) => tr_with("interp.not_differentiable", &[name]),
+        NoSignChange { ref from, ref to } => tr_with("interp.no_sign_change", &[from, to]),
+        NoConvergence(ref start) => tr_with("interp.no_convergence", &[start]),
+        LimitExceeded(limit) => match limit {
+          Limit::Depth(n) => tr_with("interp.limit_depth", &[&n]),
+          Limit::Steps(n) => tr_with("interp.limit_steps", &[&n]),
+          Limit::Bits(n) => tr_with("interp.limit_bits", &[&n]),
+        },
+        NotVariable(ref e) => tr_with("interp.not_variable", &[e]),
+      };
+      write!(f, "{}", message)
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::ast::parse;
+  use super::interpreter::{Interpreter, Value};
+  use super::lexer::lex;
+
+  #[test]
+  fn test_interpreter() {
+    let tokens = lex("1 + 2 * 3 - 10").unwrap();
+    let ast = parse(tokens).unwrap();
+    let mut interpreter = Interpreter::<Value>::new();
 
     let value = interpreter.eval(&ast).unwrap();
-    assert_eq!(value, 3);
+    assert_eq!(value, Value::Int(-3));
+  }
+
+  #[test]
+  fn test_interpreter_float() {
+    let mut interpreter = Interpreter::<Value>::new();
+    let mut eval = |s: &str| interpreter.eval(&parse(lex(s).unwrap()).unwrap());
+
+    assert_eq!(eval("7 / 2"), Ok(Value::Int(3)));
+    assert_eq!(eval("7 / 2.0"), Ok(Value::Float(3.5)));
+    assert_eq!(eval(".5 + 1e-1 * 2"), Ok(Value::Float(0.7)));
+    assert_eq!(eval("-6.02E23"), Ok(Value::Float(-6.02e23)));
+    assert!(eval("1 / 0.0").is_err());
+  }
+
+  #[test]
+  fn test_interpreter_overflow() {
+    use super::interpreter::{InterpreterError, InterpreterErrorKind};
+    use super::Loc;
+
+    let mut interpreter = Interpreter::<Value>::new();
+    let mut eval = |s: &str| interpreter.eval(&parse(lex(s).unwrap()).unwrap());
+    let overflow = |loc| Err(InterpreterError::new(InterpreterErrorKind::Overflow, loc));
+
+    assert_eq!(eval("9223372036854775807"), Ok(Value::Int(i64::MAX)));
+    assert_eq!(eval("1 + 9223372036854775808"), overflow(Loc(4, 23)));
+    assert_eq!(eval("4611686018427387904 * 2"), overflow(Loc(0, 23)));
+    assert_eq!(eval("20!"), Ok(Value::Int(2432902008176640000)));
+    assert_eq!(eval("21!"), overflow(Loc(0, 3)));
+    assert_eq!(eval("-9223372036854775807 - 2"), overflow(Loc(0, 24)));
+    assert_eq!(
+      eval("4611686018427387904 * 2.0"),
+      Ok(Value::Float(9.223372036854776e18))
+    );
+  }
+
+  #[test]
+  fn test_interpreter_bignum() {
+    use super::interpreter::Mode;
+
+    let mut interpreter = Mode::Bignum.interpreter();
+    let mut eval = |s: &str| interpreter.eval_to_string(&parse(lex(s).unwrap()).unwrap());
+
+    assert_eq!(
+      eval("4611686018427387904 * 2 + 18446744073709551616"),
+      Ok("27670116110564327424".to_string())
+    );
+    assert_eq!(
+      eval("-99999999999999999999 / 7"),
+      Ok("-14285714285714285714".to_string())
+    );
+    assert_eq!(eval("6.02e23"), Ok("602000000000000000000000".to_string()));
+    // every digit of a factorial
+    assert_eq!(
+      eval("30!"),
+      Ok("265252859812191058636308480000000".to_string())
+    );
+    assert_eq!(eval("fact(0) + 3!"), Ok("7".to_string()));
+    assert!(eval("(-1)!").is_err());
+    assert!(eval("10 / 4 * 2.5").is_err());
+    assert!(eval("1 / (2 - 2)").is_err());
+  }
+
+  #[test]
+  fn test_interpreter_rational() {
+    use super::interpreter::{InterpreterError, InterpreterErrorKind, Mode};
+    use super::Loc;
+
+    let mut interpreter = Mode::Rational.interpreter();
+    let mut eval = |s: &str| interpreter.eval_to_string(&parse(lex(s).unwrap()).unwrap());
+
+    assert_eq!(eval("7 / 2"), Ok("7/2 (3.5)".to_string()));
+    assert_eq!(eval("1/3 + 1/6"), Ok("1/2 (0.5)".to_string()));
+    assert_eq!(eval("0.1 * 30"), Ok("3".to_string()));
+    assert_eq!(eval("25! / 24!"), Ok("25".to_string()));
+    assert!(eval("(1/2)!").is_err());
+    assert_eq!(
+      eval("-2 / 3"),
+      Ok("-2/3 (-0.66666666666666666666...)".to_string())
+    );
+    assert_eq!(
+      eval("1 / (1/3 - 2/6)"),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::DivisionByZero,
+        Loc(0, 14)
+      ))
+    );
+  }
+
+  #[test]
+  fn test_interpreter_backends() {
+    use super::complex::Complex;
+    use super::interpreter::{InterpreterError, InterpreterErrorKind};
+    use super::Loc;
+
+    let ast = |s: &str| parse(lex(s).unwrap()).unwrap();
+
+    let mut int = Interpreter::<i64>::new();
+    assert_eq!(int.eval(&ast("7 / 2 + 1e3")), Ok(1003));
+    assert_eq!(
+      int.eval(&ast("1 + 0.5")),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::Domain("0.5 is not an integer".to_string()),
+        Loc(4, 7)
+      ))
+    );
+
+    let mut float = Interpreter::<f64>::new();
+    assert_eq!(float.eval(&ast("7 / 2")), Ok(3.5));
+    assert_eq!(
+      float.eval(&ast("1 / (2 - 2)")),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::DivisionByZero,
+        Loc(0, 10)
+      ))
+    );
+
+    let mut complex = Interpreter::<Complex>::new();
+    assert_eq!(
+      complex.eval(&ast("-(1 - 3) / 4")),
+      Ok(Complex::new(0.5, 0.0))
+    );
+  }
+
+  #[test]
+  fn test_interpreter_variables() {
+    use super::interpreter::{InterpreterError, InterpreterErrorKind};
+    use super::Loc;
+
+    let mut interpreter = Interpreter::<Value>::new();
+    let mut eval = |s: &str| interpreter.eval(&parse(lex(s).unwrap()).unwrap());
+
+    assert_eq!(eval("x = 3 * 4"), Ok(Value::Int(12)));
+    assert_eq!(eval("x + 1"), Ok(Value::Int(13)));
+    assert_eq!(eval("ans * 2"), Ok(Value::Int(26)));
+    assert_eq!(eval("a = b = x / 4"), Ok(Value::Int(3)));
+    assert_eq!(eval("(y = a + b) * 2"), Ok(Value::Int(12)));
+    assert_eq!(eval("y"), Ok(Value::Int(6)));
+    assert_eq!(
+      eval("x + z"),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::UndefinedVariable("z".to_string()),
+        Loc(4, 5)
+      ))
+    );
+    // a failed evaluation keeps the previous `ans`
+    assert_eq!(eval("ans"), Ok(Value::Int(6)));
+  }
+
+  #[test]
+  fn test_interpreter_builtins() {
+    use super::interpreter::{InterpreterError, InterpreterErrorKind, Mode};
+    use super::Loc;
+
+    let ast = |s: &str| parse(lex(s).unwrap()).unwrap();
+
+    let mut interpreter = Interpreter::<Value>::new();
+    let mut eval = |s: &str| interpreter.eval(&ast(s));
+    assert_eq!(eval("max(2, 3) * abs(-4)"), Ok(Value::Int(12)));
+    assert_eq!(eval("floor(2.5) + ceil(2.5)"), Ok(Value::Float(5.0)));
+    assert_eq!(eval("pow(2, 10)"), Ok(Value::Int(1024)));
+    assert_eq!(eval("sqrt(16)"), Ok(Value::Float(4.0)));
+    assert_eq!(eval("sin(pi / 2)"), Ok(Value::Float(1.0)));
+    assert_eq!(eval("ln(e)"), Ok(Value::Float(1.0)));
+    assert_eq!(
+      eval("1 + sqrt(-1)"),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::Domain(
+          "sqrt is undefined for -1.0: the argument must not be negative".to_string()
+        ),
+        Loc(4, 12)
+      ))
+    );
+    assert_eq!(
+      eval("max(1)"),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::ArityMismatch {
+          name: "max".to_string(),
+          expected: 2,
+          found: 1
+        },
+        Loc(0, 6)
+      ))
+    );
+
+    let mut rational = Mode::Rational.interpreter();
+    assert_eq!(
+      rational.eval_to_string(&ast("pow(2/3, -2) + floor(-7/2)")),
+      Ok("-7/4 (-1.75)".to_string())
+    );
+    let mut bignum = Mode::Bignum.interpreter();
+    assert_eq!(
+      bignum.eval_to_string(&ast("pow(2, 100)")),
+      Ok("1267650600228229401496703205376".to_string())
+    );
+    let mut complex = Mode::Complex.interpreter();
+    assert_eq!(
+      complex.eval_to_string(&ast("sqrt(-4) * i")),
+      Ok("-2.0".to_string())
+    );
+  }
+
+  #[test]
+  fn test_context() {
+    use super::context::Context;
+    use super::interpreter::InterpreterErrorKind;
+    use super::Loc;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let load = Rc::new(Cell::new(1));
+    let mut context = Context::new();
+    context.define_constant("cpu_count", Value::Int(8));
+    let reading = Rc::clone(&load);
+    context.define_variable("load", move || Value::Int(reading.get()));
+    context.define_function("percentile", 2, |args: &[Value]| match (args[0], args[1]) {
+      (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a * b / 100)),
+      _ => Err(InterpreterErrorKind::Domain("not integers".to_string())),
+    });
+    let mut interpreter = Interpreter::with_context(context);
+    let mut eval = |s: &str| interpreter.eval(&s.parse().unwrap()).map_err(|e| e.value);
+
+    assert_eq!(eval("percentile(cpu_count, 50) + load"), Ok(Value::Int(5)));
+    load.set(3);
+    assert_eq!(eval("load"), Ok(Value::Int(3)));
+    assert_eq!(
+      eval("cpu_count = 2"),
+      Err(InterpreterErrorKind::ReadOnly("cpu_count".to_string()))
+    );
+    assert_eq!(
+      eval("percentile(1)"),
+      Err(InterpreterErrorKind::ArityMismatch {
+        name: "percentile".to_string(),
+        expected: 2,
+        found: 1,
+      })
+    );
+    // built-in functions are still there, and unknown names say where they are
+    assert_eq!(eval("max(cpu_count, 9)"), Ok(Value::Int(9)));
+    let e = interpreter
+      .eval(&"1 + memory".parse().unwrap())
+      .unwrap_err();
+    assert_eq!(
+      (e.value, e.loc),
+      (
+        InterpreterErrorKind::UndefinedVariable("memory".to_string()),
+        Loc(4, 10)
+      )
+    );
+  }
+
+  #[test]
+  fn test_evaluate() {
+    use super::ast::{Ast, AstKind, ParseError};
+    use super::{evaluate, Error, Loc, StdError};
+
+    assert_eq!(evaluate("1 + 2 * 3"), Ok(Value::Int(7)));
+    assert_eq!(evaluate("1 +"), Err(Error::Parser(ParseError::Eof)));
+
+    let input = "2 * (1 / 0)";
+    let e = evaluate(input).unwrap_err();
+    assert!(matches!(e, Error::Interpreter(_)));
+    assert_eq!(e.to_string(), "interpreter error [C0201]");
+    assert_eq!(e.source().unwrap().to_string(), "zero division error");
+    assert_eq!(
+      e.diagnostics(input)[0].render(input, false),
+      "\
+error[C0201]: zero division error
+ --> 1:8
+  |
+1 | 2 * (1 / 0)
+  |        ^ - this evaluates to zero
+  |        |
+  |        division by zero
+"
+    );
+
+    // the tree can be taken apart from outside the crate
+    let ast = "x = 2".parse::<Ast>().unwrap();
+    assert_eq!(ast.loc(), &Loc::new(0, 5));
+    match ast.value() {
+      AstKind::Assign { name, e } => {
+        assert_eq!(name, "x");
+        assert_eq!((e.loc().start(), e.loc().end()), (4, 5));
+      }
+      e => panic!("{:?}", e),
+    }
+  }
+
+  #[test]
+  fn test_parse_partial() {
+    use super::ast::Ast;
+    use super::interpreter::{InterpreterError, InterpreterErrorKind};
+    use super::operator::OperatorTable;
+    use super::{Error, Loc, StdError};
+
+    let errors = match "1 + $ 2 * (3".parse::<Ast>() {
+      Err(Error::Multiple(errors)) => errors,
+      e => panic!("{:?}", e),
+    };
+    assert_eq!(
+      errors
+        .iter()
+        .map(|e| e.source().unwrap().to_string())
+        .collect::<Vec<_>>(),
+      vec!["4-5: invalid char '$'", "10-11: '(' is not closed"]
+    );
+    assert!(matches!("1 + $ 2".parse::<Ast>(), Err(Error::Lexer(_))));
+    assert_eq!(
+      "1 + $ 2".parse::<Ast>().unwrap_err().to_string(),
+      "lexer error [C0001]"
+    );
+    assert_eq!(
+      "1 + $ 2 * (3".parse::<Ast>().unwrap_err().to_string(),
+      "2 errors"
+    );
+
+    let (ast, errors) = Ast::parse_partial("1 + ) * 2", &OperatorTable::default());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+      Interpreter::<Value>::new().eval(&ast),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::SyntaxError,
+        Loc(4, 5)
+      ))
+    );
+  }
+
+  #[test]
+  fn test_locale() {
+    use super::ast::Ast;
+    use crate::i18n::{set_locale, Locale};
+
+    let input = "max(1) + 2 / (3 - 3)";
+    let ast = input.parse::<Ast>().unwrap();
+    let e = Interpreter::<Value>::new().eval(&ast).unwrap_err();
+    assert_eq!(e.to_string(), "'max' takes 2 arguments but 1 was given");
+    set_locale(Locale::Ja);
+    assert_eq!(e.to_string(), "'max' の引数は 2 個ですが 1 個渡されました");
+    assert_eq!(
+      e.diagnostic(&ast).render(input, false),
+      "\
+error[C0207]: 'max' の引数は 2 個ですが 1 個渡されました
+ --> 1:1
+  |
+1 | max(1) + 2 / (3 - 3)
+  | ^^^^^^ 1 個の引数で呼ばれています
+"
+    );
+    assert_eq!(
+      "1 + (2".parse::<Ast>().unwrap_err().to_string(),
+      "構文解析エラー [C0104]"
+    );
+    set_locale(Locale::En);
+  }
+
+  #[test]
+  fn test_diagnostic() {
+    use super::ast::Ast;
+
+    let input = "x = 1 + 2 / (3 - 3)";
+    let ast = input.parse::<Ast>().unwrap();
+    let e = Interpreter::<Value>::new().eval(&ast).unwrap_err();
+    assert_eq!(
+      e.diagnostic(&ast).render(input, false),
+      "\
+error[C0201]: zero division error
+ --> 1:11
+  |
+1 | x = 1 + 2 / (3 - 3)
+  |           ^  ----- this evaluates to zero
+  |           |
+  |           division by zero
+"
+    );
+
+    let input = "1 +\n  (2 * 3";
+    let e = input.parse::<Ast>().unwrap_err();
+    assert_eq!(
+      e.diagnostics(input)[0].render(input, false),
+      "\
+error[C0104]: '(' is not closed
+ --> 2:3
+  |
+2 |   (2 * 3
+  |   ^ this parenthesis is never closed
+  = help: add a ')' after the expression
+"
+    );
+
+    // the operand of a postfix operator keeps its parentheses
+    use super::interpreter::Mode;
+    let input = "(1/2)!";
+    let ast = input.parse::<Ast>().unwrap();
+    assert_eq!(ast.loc, super::Loc(0, 6));
+    let e = Mode::Rational
+      .interpreter()
+      .eval_to_string(&ast)
+      .unwrap_err();
+    assert_eq!(
+      e.diagnostic(&ast).render(input, false),
+      "\
+error[C0204]: domain error: fact is undefined for 0.5: the argument must be a whole number, 0 or more
+ --> 1:1
+  |
+1 | (1/2)!
+  | ^^^^^^ outside the domain
+"
+    );
+  }
+
+  #[test]
+  fn test_json() {
+    use super::ast::Ast;
+
+    let input = "1 +\n 2 $ 3";
+    let e = input.parse::<Ast>().unwrap_err();
+    let lines: Vec<_> = e.to_json(input).iter().map(|j| j.to_string()).collect();
+    assert_eq!(
+      lines,
+      vec![
+        r#"{"type":"error","kind":"InvalidChar","code":"C0001","message":"invalid char '$'","span":{"start":7,"end":8},"line":2,"column":4,"source":["7-8: invalid char '$'"]}"#,
+        r#"{"type":"error","kind":"RedundantExpression","code":"C0105","message":"expression after '3' is redundant","span":{"start":9,"end":10},"line":2,"column":6,"source":["9-10: expression after '3' is redundant"]}"#,
+      ]
+    );
+
+    let input = "2 * x";
+    let ast = input.parse::<Ast>().unwrap();
+    let e = Interpreter::<Value>::new().eval(&ast).unwrap_err();
+    assert_eq!(
+      e.to_json(input).to_string(),
+      r#"{"type":"error","kind":"UndefinedVariable","code":"C0205","message":"undefined variable 'x'","span":{"start":4,"end":5},"line":1,"column":5,"source":[]}"#
+    );
+
+    // the column counts characters, however wide they are on a terminal
+    let input = "日本 + $";
+    let e = input.parse::<Ast>().unwrap_err();
+    let eof = e.to_json(input).pop().unwrap().to_string();
+    assert!(eof.contains(r#""line":1,"column":7,"#), "{}", eof);
+  }
+
+  #[test]
+  fn test_interpreter_pow() {
+    use super::interpreter::{InterpreterError, InterpreterErrorKind, Mode};
+    use super::Loc;
+
+    let ast = |s: &str| parse(lex(s).unwrap()).unwrap();
+
+    let mut interpreter = Interpreter::<Value>::new();
+    let mut eval = |s: &str| interpreter.eval(&ast(s));
+    assert_eq!(eval("-2^2"), Ok(Value::Int(-4)));
+    assert_eq!(eval("(-2)^2"), Ok(Value::Int(4)));
+    assert_eq!(eval("2^3^2"), Ok(Value::Int(512)));
+    assert_eq!(eval("2**-1"), Ok(Value::Float(0.5)));
+    assert_eq!(eval("3 * 2^2 + 1"), Ok(Value::Int(13)));
+    assert_eq!(
+      eval("1 + 2^64"),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::Overflow,
+        Loc(4, 8)
+      ))
+    );
+
+    let mut bignum = Mode::Bignum.interpreter();
+    assert_eq!(
+      bignum.eval_to_string(&ast("2^64")),
+      Ok("18446744073709551616".to_string())
+    );
+    let mut rational = Mode::Rational.interpreter();
+    assert_eq!(
+      rational.eval_to_string(&ast("(2/3)^-2")),
+      Ok("9/4 (2.25)".to_string())
+    );
+  }
+
+  #[test]
+  fn test_limits() {
+    use super::ast::{Ast, ParseError};
+    use super::bignum::BigInt;
+    use super::interpreter::{InterpreterError, InterpreterErrorKind};
+    use super::limits::{Limit, Limits};
+    use super::operator::OperatorTable;
+    use super::{evaluate, Error, Loc};
+
+    // too deep an input is an error, not a stack overflow
+    for input in ["(".repeat(100_000), "-".repeat(100_000) + "1"] {
+      let (ast, errors) = Ast::parse_partial(&input, &OperatorTable::default());
+      assert_eq!(errors.len(), 1);
+      match errors[0] {
+        Error::Parser(ParseError::TooDeep(ref token, 256)) => {
+          assert_eq!(token.loc, Loc(257, 258))
+        }
+        ref e => panic!("{:?}", e),
+      }
+      let rendered = errors[0].diagnostics(&input)[0].render(&input, false);
+      let message = format!(
+        "error[C0108]: '{}' nests deeper than 256 levels",
+        &input[..1]
+      );
+      assert!(rendered.starts_with(&message), "{}", rendered);
+      assert!(Interpreter::<Value>::new().eval(&ast).is_err());
+    }
+    // just within the limit it is still evaluated
+    assert_eq!(evaluate(&("-".repeat(255) + "1")), Ok(Value::Int(-1)));
+    // while a chain of operators is as flat as it reads, however long
+    let input = vec!["1"; 1000].join(" + ");
+    assert_eq!(evaluate(&input), Ok(Value::Int(1000)));
+    let input = vec!["2"; 1000].join(" * (1 + ") + &")".repeat(999);
+    assert!(input.parse::<Ast>().is_err());
+
+    let limits = Limits {
+      depth: 4,
+      steps: 10,
+      bits: 64,
+    };
+    let (_, errors) = Ast::parse_limited("((((1))))", &OperatorTable::default(), &limits);
+    assert!(errors.is_empty());
+    let (_, errors) = Ast::parse_limited("(((((1)))))", &OperatorTable::default(), &limits);
+    assert_eq!(errors.len(), 1);
+
+    let mut interpreter = Interpreter::<BigInt>::new();
+    interpreter.set_limits(Limits { depth: 8, ..limits });
+    let mut eval = |s: &str| interpreter.eval(&s.parse().unwrap());
+    let exceeded = |limit, loc| {
+      Err(InterpreterError::new(
+        InterpreterErrorKind::LimitExceeded(limit),
+        loc,
+      ))
+    };
+    assert_eq!(eval("1 + 2 + 3 + 4").map(|n| n.to_i64()), Ok(Some(10)));
+    assert_eq!(
+      eval("1 + 2 + 3 + 4 + 5 + 6"),
+      exceeded(Limit::Steps(10), Loc(20, 21))
+    );
+    // the steps are counted anew for each evaluation
+    assert!(eval("1 + 2 + 3 + 4").is_ok());
+    assert_eq!(eval("---------1"), exceeded(Limit::Depth(8), Loc(8, 10)));
+    // a power is refused before computing it, however large
+    assert_eq!(eval("2 ^ 65"), exceeded(Limit::Bits(64), Loc(0, 6)));
+    assert_eq!(eval("2 ^ 10 ^ 100"), exceeded(Limit::Bits(64), Loc(4, 12)));
+    assert_eq!(eval("pow(3, 50)"), exceeded(Limit::Bits(64), Loc(0, 10)));
+    assert!(eval("2 ^ 63").is_ok());
+    assert_eq!(eval("1000!"), exceeded(Limit::Bits(64), Loc(0, 5)));
+    assert_eq!(eval("21!"), exceeded(Limit::Bits(64), Loc(0, 3)));
+    assert_eq!(
+      eval("4294967296 * 4294967296"),
+      exceeded(Limit::Bits(64), Loc(0, 23))
+    );
+
+    // the defaults hold back a power too large to compute in reasonable time
+    let mut interpreter = Interpreter::<BigInt>::new();
+    assert_eq!(
+      interpreter.eval(&"1 + 2 ^ 100000000".parse().unwrap()),
+      exceeded(Limit::Bits(1 << 16), Loc(4, 17))
+    );
+    assert_eq!(
+      interpreter.eval(&"fact(100000000)".parse().unwrap()),
+      exceeded(Limit::Bits(1 << 16), Loc(0, 15))
+    );
+  }
+
+  #[test]
+  fn test_deep() {
+    use super::ast::{Ast, AstKind};
+    use super::limits::Limits;
+    use super::operator::OperatorTable;
+    use super::vm::Code;
+    use super::Loc;
+
+    // neither parsing, evaluating nor dropping recurses, so only the limits bound the depth
+    let n = 100_000;
+    let limits = Limits {
+      depth: n + 1,
+      ..Limits::default()
+    };
+    let table = OperatorTable::default();
+    let mut interpreter = Interpreter::<Value>::new();
+    interpreter.set_limits(limits);
+    let cases = [
+      ("(".repeat(n) + "1" + &")".repeat(n), 1),
+      ("-".repeat(n) + "1", 1),
+      (vec!["1"; n].join(" + "), n as i64),
+      ("1 ^ ".repeat(n) + "2", 1),
+      ("max(".repeat(n) + "1" + &", 2)".repeat(n), 2),
+      ("x = ".repeat(n) + "3", 3),
+    ];
+    for (input, expected) in cases {
+      let (ast, errors) = Ast::parse_limited(&input, &table, &limits);
+      assert!(errors.is_empty());
+      assert_eq!(interpreter.eval(&ast), Ok(Value::Int(expected)));
+    }
+
+    // with the same tree and spans as ever
+    let (ast, _) = Ast::parse_limited(&("-".repeat(n) + "1"), &table, &limits);
+    let mut node = &ast;
+    for i in 0..n {
+      assert_eq!(node.loc, Loc(i, n + 1));
+      node = match node.value {
+        AstKind::UniOp { ref e, .. } => e,
+        ref e => panic!("{:?}", e),
+      };
+    }
+    assert_eq!(node.value, AstKind::Num(1u64.into()));
+
+    // nor does any other pass over a tree
+    let input = "-".repeat(n - 1) + "(1 / 0)";
+    let (ast, _) = Ast::parse_limited(&input, &table, &limits);
+    let error = interpreter.eval(&ast).unwrap_err();
+    assert_eq!(error.loc, Loc(n, n + 5));
+    assert!(error
+      .diagnostic(&ast)
+      .render(&input, false)
+      .contains("C0201"));
+    assert_eq!(interpreter.run(&Code::compile(&ast)), Err(error));
+    assert_eq!(ast.to_string(), input);
+    let copy = ast.clone();
+    assert_eq!(copy, ast);
+    assert_eq!(interpreter.simplify(&ast), ast);
+    let d = interpreter.diff(
+      &Ast::parse_limited(&("-".repeat(n) + "x"), &table, &limits).0,
+      "x",
+    );
+    assert!(d.is_ok());
+
+    // and every entry point takes limits in one call
+    let input = "-".repeat(1000) + "(1 / 0)";
+    let limits = Limits {
+      depth: 2000,
+      ..Limits::default()
+    };
+    assert!(input.parse::<Ast>().is_err());
+    assert!(Ast::from_str_limited(&input, &limits).is_ok());
+    assert!(super::ast::parse_limited(lex(&input).unwrap(), &table, &limits).is_ok());
+    let error = super::evaluate_limited(&input, &limits).unwrap_err();
+    let rendered = error.diagnostics(&input)[0].render(&input, false);
+    assert!(rendered.contains("C0201") && rendered.contains("division by zero"));
   }
 }
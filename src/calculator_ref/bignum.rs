@@ -0,0 +1,522 @@
+// arbitrary-precision integers for the bignum mode of the interpreter
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+// sign and magnitude; `mag` is little-endian base 2^32 without trailing zero limbs,
+// so zero is an empty `mag` and never negative
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+  negative: bool,
+  mag: Vec<u32>,
+}
+
+impl BigInt {
+  pub fn zero() -> Self {
+    BigInt {
+      negative: false,
+      mag: Vec::new(),
+    }
+  }
+
+  fn from_mag(negative: bool, mut mag: Vec<u32>) -> Self {
+    trim(&mut mag);
+    let negative = negative && !mag.is_empty();
+    BigInt { negative, mag }
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.mag.is_empty()
+  }
+
+  pub fn is_negative(&self) -> bool {
+    self.negative
+  }
+
+  pub fn abs(&self) -> Self {
+    BigInt::from_mag(false, self.mag.clone())
+  }
+
+  pub fn to_i64(&self) -> Option<i64> {
+    if self.mag.len() > 2 {
+      return None;
+    }
+    let m = self
+      .mag
+      .iter()
+      .rev()
+      .fold(0u64, |acc, &limb| (acc << 32) | limb as u64);
+    if self.negative {
+      if m <= i64::MAX as u64 + 1 {
+        Some((m as i64).wrapping_neg())
+      } else {
+        None
+      }
+    } else if m <= i64::MAX as u64 {
+      Some(m as i64)
+    } else {
+      None
+    }
+  }
+
+  pub fn to_f64(&self) -> f64 {
+    let m = self
+      .mag
+      .iter()
+      .rev()
+      .fold(0.0, |acc, &limb| acc * 4_294_967_296.0 + limb as f64);
+    if self.negative {
+      -m
+    } else {
+      m
+    }
+  }
+
+  // the number of bits of the magnitude, 0 for zero
+  pub fn bits(&self) -> u64 {
+    match self.mag.last() {
+      Some(top) => self.mag.len() as u64 * 32 - top.leading_zeros() as u64,
+      None => 0,
+    }
+  }
+
+  pub fn pow(&self, mut exp: u32) -> BigInt {
+    let mut base = self.clone();
+    let mut ret = BigInt::from(1u64);
+    while exp > 0 {
+      if exp & 1 == 1 {
+        ret = &ret * &base;
+      }
+      base = &base * &base;
+      exp >>= 1;
+    }
+    ret
+  }
+
+  // always non-negative; gcd(0, 0) is 0
+  pub fn gcd(&self, other: &BigInt) -> BigInt {
+    let (mut a, mut b) = (self.abs(), other.abs());
+    while !b.is_zero() {
+      let r = a.div_rem(&b).unwrap().1;
+      a = b;
+      b = r;
+    }
+    a
+  }
+
+  // truncating division like `i64`, the remainder takes the sign of `self`
+  pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+    if other.is_zero() {
+      return None;
+    }
+    let (q, r) = div_rem_mag(&self.mag, &other.mag);
+    Some((
+      BigInt::from_mag(self.negative != other.negative, q),
+      BigInt::from_mag(self.negative, r),
+    ))
+  }
+}
+
+impl From<u64> for BigInt {
+  fn from(n: u64) -> Self {
+    BigInt::from_mag(false, vec![n as u32, (n >> 32) as u32])
+  }
+}
+
+impl From<i64> for BigInt {
+  fn from(n: i64) -> Self {
+    let m = BigInt::from(n.unsigned_abs());
+    if n < 0 {
+      -m
+    } else {
+      m
+    }
+  }
+}
+
+impl Ord for BigInt {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self.negative, other.negative) {
+      (false, true) => Ordering::Greater,
+      (true, false) => Ordering::Less,
+      (false, false) => cmp_mag(&self.mag, &other.mag),
+      (true, true) => cmp_mag(&other.mag, &self.mag),
+    }
+  }
+}
+
+impl PartialOrd for BigInt {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Neg for BigInt {
+  type Output = BigInt;
+  fn neg(self) -> BigInt {
+    let negative = !self.negative;
+    BigInt::from_mag(negative, self.mag)
+  }
+}
+
+impl<'a> Add<&'a BigInt> for &'a BigInt {
+  type Output = BigInt;
+  fn add(self, other: &BigInt) -> BigInt {
+    if self.negative == other.negative {
+      return BigInt::from_mag(self.negative, add_mag(&self.mag, &other.mag));
+    }
+    // different signs: subtract the smaller magnitude from the larger one
+    match cmp_mag(&self.mag, &other.mag) {
+      Ordering::Less => BigInt::from_mag(other.negative, sub_mag(&other.mag, &self.mag)),
+      _ => BigInt::from_mag(self.negative, sub_mag(&self.mag, &other.mag)),
+    }
+  }
+}
+
+impl<'a> Sub<&'a BigInt> for &'a BigInt {
+  type Output = BigInt;
+  fn sub(self, other: &BigInt) -> BigInt {
+    self + &-other.clone()
+  }
+}
+
+impl<'a> Mul<&'a BigInt> for &'a BigInt {
+  type Output = BigInt;
+  fn mul(self, other: &BigInt) -> BigInt {
+    BigInt::from_mag(
+      self.negative != other.negative,
+      mul_mag(&self.mag, &other.mag),
+    )
+  }
+}
+
+// the largest power of ten that fits in a limb, used to convert from and to decimal
+const DECIMAL_BASE: u32 = 1_000_000_000;
+const DECIMAL_DIGITS: usize = 9;
+
+impl fmt::Display for BigInt {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut chunks = Vec::new();
+    let mut mag = self.mag.clone();
+    while !mag.is_empty() {
+      let (q, r) = div_rem_small(&mag, DECIMAL_BASE);
+      chunks.push(r);
+      mag = q;
+    }
+    let mut s = match chunks.pop() {
+      Some(top) => top.to_string(),
+      None => "0".to_string(),
+    };
+    for chunk in chunks.iter().rev() {
+      s.push_str(&format!("{:09}", chunk));
+    }
+    f.pad_integral(!self.negative, "", &s)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBigIntError;
+
+impl fmt::Display for ParseBigIntError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "invalid digit found in string")
+  }
+}
+
+impl FromStr for BigInt {
+  type Err = ParseBigIntError;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (negative, digits) = match s.strip_prefix('-') {
+      Some(digits) => (true, digits),
+      None => (false, s),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+      return Err(ParseBigIntError);
+    }
+    let mut mag = Vec::new();
+    // the first chunk takes the leftover digits so the rest are exactly 9 wide
+    let first = digits.len() % DECIMAL_DIGITS;
+    let chunks = std::iter::once(&digits[..first]).chain(
+      digits.as_bytes()[first..]
+        .chunks(DECIMAL_DIGITS)
+        .map(|c| std::str::from_utf8(c).unwrap()),
+    );
+    for chunk in chunks.filter(|c| !c.is_empty()) {
+      let scale = 10u32.pow(chunk.len() as u32);
+      mul_add_small(&mut mag, scale, chunk.parse().unwrap());
+    }
+    Ok(BigInt::from_mag(negative, mag))
+  }
+}
+
+fn trim(mag: &mut Vec<u32>) {
+  while mag.last() == Some(&0) {
+    mag.pop();
+  }
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+  a.len()
+    .cmp(&b.len())
+    .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+  let (a, b) = if a.len() < b.len() { (b, a) } else { (a, b) };
+  let mut ret = Vec::with_capacity(a.len() + 1);
+  let mut carry = 0u64;
+  for (i, &x) in a.iter().enumerate() {
+    let sum = x as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+    ret.push(sum as u32);
+    carry = sum >> 32;
+  }
+  ret.push(carry as u32);
+  trim(&mut ret);
+  ret
+}
+
+// requires `a >= b`
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+  let mut ret = Vec::with_capacity(a.len());
+  let mut borrow = 0i64;
+  for (i, &x) in a.iter().enumerate() {
+    let mut diff = x as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+    borrow = 0;
+    if diff < 0 {
+      diff += 1 << 32;
+      borrow = 1;
+    }
+    ret.push(diff as u32);
+  }
+  trim(&mut ret);
+  ret
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+  if a.is_empty() || b.is_empty() {
+    return Vec::new();
+  }
+  let mut ret = vec![0u32; a.len() + b.len()];
+  for (i, &x) in a.iter().enumerate() {
+    let mut carry = 0u64;
+    for (j, &y) in b.iter().enumerate() {
+      let t = x as u64 * y as u64 + ret[i + j] as u64 + carry;
+      ret[i + j] = t as u32;
+      carry = t >> 32;
+    }
+    ret[i + b.len()] = carry as u32;
+  }
+  trim(&mut ret);
+  ret
+}
+
+// mag = mag * m + a
+fn mul_add_small(mag: &mut Vec<u32>, m: u32, a: u32) {
+  let mut carry = a as u64;
+  for limb in mag.iter_mut() {
+    let t = *limb as u64 * m as u64 + carry;
+    *limb = t as u32;
+    carry = t >> 32;
+  }
+  if carry > 0 {
+    mag.push(carry as u32);
+  }
+}
+
+fn div_rem_small(a: &[u32], d: u32) -> (Vec<u32>, u32) {
+  let mut q = vec![0u32; a.len()];
+  let mut r = 0u64;
+  for (i, &x) in a.iter().enumerate().rev() {
+    let t = (r << 32) | x as u64;
+    q[i] = (t / d as u64) as u32;
+    r = t % d as u64;
+  }
+  trim(&mut q);
+  (q, r as u32)
+}
+
+// Knuth's algorithm D (TAOCP 4.3.1): long division a limb of the quotient at a time, each
+// estimated from the top limbs of the remainder and corrected at most a few times, so it
+// takes about `a.len() * b.len()` steps. `b` must not be zero
+fn div_rem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+  if cmp_mag(a, b) == Ordering::Less {
+    return (Vec::new(), a.to_vec());
+  }
+  if b.len() == 1 {
+    let (q, r) = div_rem_small(a, b[0]);
+    let mut r = vec![r];
+    trim(&mut r);
+    return (q, r);
+  }
+  // both shifted so the top bit of the divisor is set, which keeps the estimates close
+  let shift = b.last().unwrap().leading_zeros();
+  let mut v = shl_mag(b, shift);
+  v.pop();
+  let mut u = shl_mag(a, shift);
+  let n = v.len();
+  let (v1, v2) = (v[n - 1] as u64, v[n - 2] as u64);
+  let mut q = vec![0u32; u.len() - n];
+  for j in (0..q.len()).rev() {
+    // the estimate from the top two limbs is at most two too large after this
+    let top = (u[j + n] as u64) << 32 | u[j + n - 1] as u64;
+    let (mut qhat, mut rhat) = (top / v1, top % v1);
+    while qhat >> 32 != 0 || qhat * v2 > (rhat << 32 | u[j + n - 2] as u64) {
+      qhat -= 1;
+      rhat += v1;
+      if rhat >> 32 != 0 {
+        break;
+      }
+    }
+    // u[j..=j + n] -= qhat * v
+    let (mut borrow, mut carry) = (0i64, 0u64);
+    for i in 0..n {
+      let p = qhat * v[i] as u64 + carry;
+      carry = p >> 32;
+      let t = u[i + j] as i64 - borrow - (p & 0xffff_ffff) as i64;
+      u[i + j] = t as u32;
+      borrow = (t < 0) as i64;
+    }
+    let t = u[j + n] as i64 - borrow - carry as i64;
+    u[j + n] = t as u32;
+    // and at most one too large after that, which the remainder going negative shows
+    if t < 0 {
+      qhat -= 1;
+      let mut carry = 0u64;
+      for i in 0..n {
+        let sum = u[i + j] as u64 + v[i] as u64 + carry;
+        u[i + j] = sum as u32;
+        carry = sum >> 32;
+      }
+      u[j + n] = u[j + n].wrapping_add(carry as u32);
+    }
+    q[j] = qhat as u32;
+  }
+  u.truncate(n);
+  let mut r = shr_mag(&u, shift);
+  trim(&mut q);
+  trim(&mut r);
+  (q, r)
+}
+
+// `a << shift` for `shift < 32`, one limb longer than `a`
+fn shl_mag(a: &[u32], shift: u32) -> Vec<u32> {
+  let mut ret = Vec::with_capacity(a.len() + 1);
+  let mut carry = 0u32;
+  for &x in a {
+    ret.push(x << shift | carry);
+    carry = if shift == 0 { 0 } else { x >> (32 - shift) };
+  }
+  ret.push(carry);
+  ret
+}
+
+// `a >> shift` for `shift < 32`
+fn shr_mag(a: &[u32], shift: u32) -> Vec<u32> {
+  if shift == 0 {
+    return a.to_vec();
+  }
+  (0..a.len())
+    .map(|i| a[i] >> shift | a.get(i + 1).map_or(0, |&next| next << (32 - shift)))
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn big(s: &str) -> BigInt {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn test_parse_and_display() {
+    for s in &[
+      "0",
+      "7",
+      "-42",
+      "4294967296",
+      "123456789012345678901234567890",
+    ] {
+      assert_eq!(big(s).to_string(), *s);
+    }
+    assert_eq!(big("-0"), BigInt::zero());
+    assert_eq!(big("000012").to_string(), "12");
+    assert_eq!("12a".parse::<BigInt>(), Err(ParseBigIntError));
+  }
+
+  #[test]
+  fn test_arithmetic() {
+    let a = big("340282366920938463463374607431768211456"); // 2^128
+    let b = big("-18446744073709551617"); // -(2^64 + 1)
+    assert_eq!(
+      (&a + &b).to_string(),
+      "340282366920938463444927863358058659839"
+    );
+    assert_eq!(
+      (&b - &a).to_string(),
+      "-340282366920938463481821351505477763073"
+    );
+    assert_eq!(
+      (&a * &b).to_string(),
+      "-6277101735386680764176071790128604879565730051895802724352"
+    );
+    let (q, r) = a.div_rem(&b).unwrap();
+    assert_eq!(q.to_string(), "-18446744073709551615");
+    assert_eq!(r.to_string(), "1");
+    assert_eq!(a.div_rem(&BigInt::zero()), None);
+    assert!(b < BigInt::zero() && BigInt::zero() < a);
+    assert_eq!(BigInt::from(2i64).pow(128), a);
+    assert_eq!(big("-12").gcd(&big("18")), big("6"));
+  }
+
+  #[test]
+  fn test_div_rem() {
+    // limbs of all ones and of the top bit alone are where the quotient estimates are off
+    // the most, and where they have to be corrected
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut limb = || {
+      seed ^= seed << 13;
+      seed ^= seed >> 7;
+      seed ^= seed << 17;
+      match seed % 4 {
+        0 => u32::MAX,
+        1 => 1 << 31,
+        _ => (seed >> 32) as u32,
+      }
+    };
+    for _ in 0..2000 {
+      let a = BigInt::from_mag(false, (0..1 + limb() % 9).map(|_| limb()).collect());
+      let b = BigInt::from_mag(true, (0..1 + limb() % 5).map(|_| limb()).collect());
+      if b.is_zero() {
+        continue;
+      }
+      let (q, r) = a.div_rem(&b).unwrap();
+      assert_eq!(&(&q * &b) + &r, a, "{} / {}", a, b);
+      assert!(cmp_mag(&r.mag, &b.mag) == Ordering::Less, "{} / {}", a, b);
+    }
+  }
+
+  // a gcd of numbers near the largest the limits allow, as each fraction is reduced by,
+  // takes a moment rather than the seconds a bit at a time took
+  #[test]
+  fn test_gcd_large() {
+    let a = &BigInt::from(3i64).pow(20_000) + &BigInt::from(1i64);
+    let b = &BigInt::from(2i64).pow(32_000) + &BigInt::from(7i64);
+    let start = std::time::Instant::now();
+    assert_eq!(a.gcd(&b), BigInt::from(1i64));
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+  }
+
+  #[test]
+  fn test_conversion() {
+    assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+    assert_eq!(BigInt::from(i64::MAX).to_i64(), Some(i64::MAX));
+    assert_eq!(
+      (&BigInt::from(i64::MAX) + &BigInt::from(1i64)).to_i64(),
+      None
+    );
+    assert_eq!(big("-4294967296").to_f64(), -4294967296.0);
+  }
+}
@@ -0,0 +1,247 @@
+// the standard library of functions callable as `name(arg, ...)`
+
+use super::interpreter::InterpreterErrorKind;
+use super::number::Number;
+use crate::i18n::{tr, tr_with};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+type Result<T> = std::result::Result<T, InterpreterErrorKind>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Func {
+  Sin,
+  Cos,
+  Tan,
+  Asin,
+  Acos,
+  Atan,
+  Atan2,
+  Exp,
+  Ln,
+  Log,
+  Log2,
+  Log10,
+  Sqrt,
+  Abs,
+  Floor,
+  Ceil,
+  Fact,
+  Min,
+  Max,
+  Pow,
+}
+
+pub const FUNCS: [Func; 20] = [
+  Func::Sin,
+  Func::Cos,
+  Func::Tan,
+  Func::Asin,
+  Func::Acos,
+  Func::Atan,
+  Func::Atan2,
+  Func::Exp,
+  Func::Ln,
+  Func::Log,
+  Func::Log2,
+  Func::Log10,
+  Func::Sqrt,
+  Func::Abs,
+  Func::Floor,
+  Func::Ceil,
+  Func::Fact,
+  Func::Min,
+  Func::Max,
+  Func::Pow,
+];
+
+impl Func {
+  pub fn name(self) -> &'static str {
+    match self {
+      Func::Sin => "sin",
+      Func::Cos => "cos",
+      Func::Tan => "tan",
+      Func::Asin => "asin",
+      Func::Acos => "acos",
+      Func::Atan => "atan",
+      Func::Atan2 => "atan2",
+      Func::Exp => "exp",
+      Func::Ln => "ln",
+      Func::Log => "log",
+      Func::Log2 => "log2",
+      Func::Log10 => "log10",
+      Func::Sqrt => "sqrt",
+      Func::Abs => "abs",
+      Func::Floor => "floor",
+      Func::Ceil => "ceil",
+      Func::Fact => "fact",
+      Func::Min => "min",
+      Func::Max => "max",
+      Func::Pow => "pow",
+    }
+  }
+
+  pub fn arity(self) -> usize {
+    match self {
+      Func::Atan2 | Func::Log | Func::Min | Func::Max | Func::Pow => 2,
+      _ => 1,
+    }
+  }
+
+  // the function on real numbers, failing where it is undefined rather than giving NaN
+  pub fn eval_f64(self, x: f64) -> Result<f64> {
+    let domain = |reason: &'static str| {
+      Err(InterpreterErrorKind::Domain(tr_with(
+        "domain.undefined_for",
+        &[&self.name(), &format!("{:?}", x), &tr(reason)],
+      )))
+    };
+    let y = match self {
+      Func::Sin => x.sin(),
+      Func::Cos => x.cos(),
+      Func::Tan => x.tan(),
+      Func::Asin | Func::Acos if !(-1.0..=1.0).contains(&x) => return domain("domain.between_one"),
+      Func::Asin => x.asin(),
+      Func::Acos => x.acos(),
+      Func::Atan => x.atan(),
+      Func::Exp => x.exp(),
+      Func::Ln | Func::Log2 | Func::Log10 if x <= 0.0 => return domain("domain.positive"),
+      Func::Ln => x.ln(),
+      Func::Log2 => x.log2(),
+      Func::Log10 => x.log10(),
+      Func::Sqrt if x < 0.0 => return domain("domain.not_negative"),
+      Func::Sqrt => x.sqrt(),
+      Func::Abs => x.abs(),
+      Func::Floor => x.floor(),
+      Func::Ceil => x.ceil(),
+      Func::Fact if x < 0.0 || x.fract() != 0.0 => return domain("domain.natural"),
+      // past 170! a float is infinite, which is reported as an overflow
+      Func::Fact if x > 170.0 => return Err(InterpreterErrorKind::Overflow),
+      Func::Fact => (2..=x as u32).map(f64::from).product(),
+      Func::Atan2 | Func::Log | Func::Min | Func::Max | Func::Pow => {
+        unreachable!("{} takes two arguments", self.name())
+      }
+    };
+    finite(y)
+  }
+}
+
+impl fmt::Display for Func {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for Func {
+  type Err = ();
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    FUNCS.iter().copied().find(|f| f.name() == s).ok_or(())
+  }
+}
+
+pub fn call<N: Number>(name: &str, args: &[N]) -> Result<N> {
+  let f: Func = name
+    .parse()
+    .map_err(|_| InterpreterErrorKind::UnknownFunction(name.to_string()))?;
+  call_func(f, args)
+}
+
+// `call` with the name already looked up
+pub fn call_func<N: Number>(f: Func, args: &[N]) -> Result<N> {
+  if args.len() != f.arity() {
+    return Err(InterpreterErrorKind::ArityMismatch {
+      name: f.name().to_string(),
+      expected: f.arity(),
+      found: args.len(),
+    });
+  }
+  match f {
+    Func::Min | Func::Max => {
+      let (a, b) = (&args[0], &args[1]);
+      let a_is_less = a.compare(b)? == Ordering::Less;
+      Ok(if a_is_less == (f == Func::Min) {
+        a.clone()
+      } else {
+        b.clone()
+      })
+    }
+    Func::Pow => args[0].pow(&args[1]),
+    Func::Atan2 => {
+      let (y, x) = (real(f, &args[0])?, real(f, &args[1])?);
+      N::from_float(y.atan2(x))
+    }
+    Func::Log => {
+      let (x, base) = (real(f, &args[0])?, real(f, &args[1])?);
+      if x <= 0.0 || base <= 0.0 || base == 1.0 {
+        return Err(InterpreterErrorKind::Domain(tr_with(
+          "domain.log",
+          &[&format!("{:?}", x), &format!("{:?}", base)],
+        )));
+      }
+      N::from_float(finite(x.log(base))?)
+    }
+    _ => args[0].apply(f),
+  }
+}
+
+// the default `Number::apply`: convert to a float, compute, and convert back
+pub fn apply_real<N: Number>(f: Func, n: &N) -> Result<N> {
+  N::from_float(f.eval_f64(real(f, n)?)?)
+}
+
+fn real<N: Number>(f: Func, n: &N) -> Result<f64> {
+  n.to_f64().ok_or(InterpreterErrorKind::Unsupported {
+    op: f.name(),
+    mode: N::NAME,
+  })
+}
+
+pub fn finite(y: f64) -> Result<f64> {
+  if y.is_infinite() {
+    Err(InterpreterErrorKind::Overflow)
+  } else {
+    Ok(y)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_call() {
+    assert_eq!(call("max", &[2.0, -3.0]), Ok(2.0));
+    assert_eq!(call("min", &[2.0, -3.0]), Ok(-3.0));
+    assert_eq!(call("sqrt", &[16.0]), Ok(4.0));
+    assert_eq!(call("log", &[8.0, 2.0]), Ok(3.0));
+    assert_eq!(call("floor", &[-2.5]), Ok(-3.0));
+    assert_eq!(call("fact", &[5.0]), Ok(120.0));
+    assert_eq!(
+      call::<f64>("fact", &[171.0]),
+      Err(InterpreterErrorKind::Overflow)
+    );
+    assert_eq!(
+      call::<f64>("sqrt", &[-1.0]),
+      Err(InterpreterErrorKind::Domain(
+        "sqrt is undefined for -1.0: the argument must not be negative".to_string()
+      ))
+    );
+    assert_eq!(
+      call("max", &[1.0]),
+      Err(InterpreterErrorKind::ArityMismatch {
+        name: "max".to_string(),
+        expected: 2,
+        found: 1
+      })
+    );
+    assert_eq!(
+      call::<f64>("nope", &[]),
+      Err(InterpreterErrorKind::UnknownFunction("nope".to_string()))
+    );
+    assert_eq!(
+      call::<f64>("exp", &[1000.0]),
+      Err(InterpreterErrorKind::Overflow)
+    );
+  }
+}
@@ -0,0 +1,65 @@
+// the error codes with the keys of their long descriptions in the message catalog, shown
+// by `:explain` and `--explain`.
+// codes are never reused or renumbered: C00xx are from the lexer, C01xx from the parser
+// and C02xx from the interpreter
+
+use crate::i18n::tr;
+
+const CODES: [(&str, &str); 25] = [
+  ("C0001", "explain.C0001"),
+  ("C0002", "explain.C0002"),
+  ("C0003", "explain.C0003"),
+  ("C0101", "explain.C0101"),
+  ("C0102", "explain.C0102"),
+  ("C0103", "explain.C0103"),
+  ("C0104", "explain.C0104"),
+  ("C0105", "explain.C0105"),
+  ("C0106", "explain.C0106"),
+  ("C0107", "explain.C0107"),
+  ("C0108", "explain.C0108"),
+  ("C0201", "explain.C0201"),
+  ("C0202", "explain.C0202"),
+  ("C0203", "explain.C0203"),
+  ("C0204", "explain.C0204"),
+  ("C0205", "explain.C0205"),
+  ("C0206", "explain.C0206"),
+  ("C0207", "explain.C0207"),
+  ("C0208", "explain.C0208"),
+  ("C0209", "explain.C0209"),
+  ("C0210", "explain.C0210"),
+  ("C0211", "explain.C0211"),
+  ("C0212", "explain.C0212"),
+  ("C0213", "explain.C0213"),
+  ("C0214", "explain.C0214"),
+];
+
+pub fn explain(code: &str) -> Option<&'static str> {
+  CODES
+    .iter()
+    .find(|(c, _)| c.eq_ignore_ascii_case(code))
+    .map(|&(_, key)| tr(key))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::i18n::{set_locale, Locale};
+
+  #[test]
+  fn test_explain() {
+    assert!(explain("C0201").unwrap().starts_with("A division by zero."));
+    assert!(explain("c0001").is_some());
+    assert_eq!(explain("C9999"), None);
+    // codes are unique and in order
+    assert!(CODES.windows(2).all(|w| w[0].0 < w[1].0));
+    // and each has a description in every language
+    for locale in [Locale::En, Locale::Ja] {
+      set_locale(locale);
+      assert!(CODES.iter().all(|&(_, key)| tr(key) != key));
+    }
+    assert!(explain("C0201")
+      .unwrap()
+      .starts_with("ゼロによる除算です。"));
+    set_locale(Locale::En);
+  }
+}
@@ -0,0 +1,164 @@
+// complex numbers for the complex mode of the interpreter
+
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+  pub re: f64,
+  pub im: f64,
+}
+
+impl Complex {
+  pub fn new(re: f64, im: f64) -> Self {
+    Complex { re, im }
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.re == 0.0 && self.im == 0.0
+  }
+
+  pub fn norm(self) -> f64 {
+    self.re.hypot(self.im)
+  }
+
+  pub fn arg(self) -> f64 {
+    self.im.atan2(self.re)
+  }
+
+  pub fn exp(self) -> Complex {
+    let r = self.re.exp();
+    Complex::new(r * self.im.cos(), r * self.im.sin())
+  }
+
+  // the principal branch, with the imaginary part in (-pi, pi]
+  pub fn ln(self) -> Complex {
+    Complex::new(self.norm().ln(), self.arg())
+  }
+
+  // repeated squaring keeps small integral powers exact, e.g. `i^2` is exactly `-1`
+  pub fn powi(self, mut exp: u32) -> Complex {
+    let mut base = self;
+    let mut ret = Complex::new(1.0, 0.0);
+    while exp > 0 {
+      if exp & 1 == 1 {
+        ret = ret * base;
+      }
+      base = base * base;
+      exp >>= 1;
+    }
+    ret
+  }
+
+  // the principal root, computed without trigonometry so `sqrt(-4)` is exactly `2i`
+  pub fn sqrt(self) -> Complex {
+    if self.is_zero() {
+      return self;
+    }
+    let t = ((self.norm() + self.re.abs()) / 2.0).sqrt();
+    if self.re >= 0.0 {
+      Complex::new(t, self.im / (2.0 * t))
+    } else {
+      // `-0.0` counts as positive, so `sqrt(-4)` is `2i` even though `-4` is `-4-0i`
+      let t_im = if self.im < 0.0 { -t } else { t };
+      Complex::new(self.im.abs() / (2.0 * t), t_im)
+    }
+  }
+
+  pub fn sin(self) -> Complex {
+    Complex::new(
+      self.re.sin() * self.im.cosh(),
+      self.re.cos() * self.im.sinh(),
+    )
+  }
+
+  pub fn cos(self) -> Complex {
+    Complex::new(
+      self.re.cos() * self.im.cosh(),
+      -self.re.sin() * self.im.sinh(),
+    )
+  }
+}
+
+impl Neg for Complex {
+  type Output = Complex;
+  fn neg(self) -> Complex {
+    Complex::new(-self.re, -self.im)
+  }
+}
+
+impl Add for Complex {
+  type Output = Complex;
+  fn add(self, other: Complex) -> Complex {
+    Complex::new(self.re + other.re, self.im + other.im)
+  }
+}
+
+impl Sub for Complex {
+  type Output = Complex;
+  fn sub(self, other: Complex) -> Complex {
+    Complex::new(self.re - other.re, self.im - other.im)
+  }
+}
+
+impl Mul for Complex {
+  type Output = Complex;
+  fn mul(self, other: Complex) -> Complex {
+    Complex::new(
+      self.re * other.re - self.im * other.im,
+      self.re * other.im + self.im * other.re,
+    )
+  }
+}
+
+// like `f64`, dividing by zero gives NaN parts rather than panicking
+impl Div for Complex {
+  type Output = Complex;
+  fn div(self, other: Complex) -> Complex {
+    let d = other.re * other.re + other.im * other.im;
+    Complex::new(
+      (self.re * other.re + self.im * other.im) / d,
+      (self.im * other.re - self.re * other.im) / d,
+    )
+  }
+}
+
+impl fmt::Display for Complex {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    // `{:?}` keeps the fraction part visible like the float results do
+    let Complex { re, im } = *self;
+    if im == 0.0 {
+      write!(f, "{:?}", re)
+    } else if re == 0.0 {
+      write!(f, "{:?}i", im)
+    } else if im < 0.0 {
+      write!(f, "{:?}-{:?}i", re, -im)
+    } else {
+      write!(f, "{:?}+{:?}i", re, im)
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_complex() {
+    let a = Complex::new(1.0, 2.0);
+    let b = Complex::new(3.0, -1.0);
+    assert_eq!(a + b, Complex::new(4.0, 1.0));
+    assert_eq!(a - b, Complex::new(-2.0, 3.0));
+    assert_eq!(a * b, Complex::new(5.0, 5.0));
+    assert_eq!(a * b / b, a);
+    assert_eq!(-a, Complex::new(-1.0, -2.0));
+    assert_eq!(Complex::new(-4.0, 0.0).sqrt(), Complex::new(0.0, 2.0));
+    assert_eq!(Complex::new(0.0, 0.0).exp(), Complex::new(1.0, 0.0));
+    assert_eq!(Complex::new(3.0, 4.0).norm(), 5.0);
+    assert_eq!(Complex::new(0.0, 1.0).powi(2), Complex::new(-1.0, 0.0));
+    assert_eq!(a.to_string(), "1.0+2.0i");
+    assert_eq!(b.to_string(), "3.0-1.0i");
+    assert_eq!(Complex::new(0.0, -1.0).to_string(), "-1.0i");
+    assert_eq!(Complex::new(2.5, 0.0).to_string(), "2.5");
+  }
+}
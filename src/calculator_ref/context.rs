@@ -0,0 +1,108 @@
+// the names a host program defines for an interpreter: constants, read-only variables
+// and native functions. they are looked up before the interpreter's own variables and the
+// built-in constants and functions, and cannot be assigned to
+
+use super::interpreter::InterpreterErrorKind;
+use std::collections::HashMap;
+
+type Result<T> = std::result::Result<T, InterpreterErrorKind>;
+
+pub type NativeFn<N> = Box<dyn Fn(&[N]) -> Result<N>>;
+
+struct Native<N> {
+  arity: usize,
+  f: NativeFn<N>,
+}
+
+enum Entry<N> {
+  Constant(N),
+  // read again on every use, for values such as a load average that change
+  Variable(Box<dyn Fn() -> N>),
+}
+
+pub struct Context<N> {
+  values: HashMap<String, Entry<N>>,
+  functions: HashMap<String, Native<N>>,
+}
+
+impl<N> Default for Context<N> {
+  fn default() -> Self {
+    Context {
+      values: HashMap::new(),
+      functions: HashMap::new(),
+    }
+  }
+}
+
+impl<N: Clone> Context<N> {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // the names are those the lexer reads as one identifier, or they could never be used
+  pub fn define_constant(&mut self, name: &str, value: N) {
+    assert_ident(name);
+    self.values.insert(name.to_string(), Entry::Constant(value));
+  }
+
+  pub fn define_variable(&mut self, name: &str, read: impl Fn() -> N + 'static) {
+    assert_ident(name);
+    self
+      .values
+      .insert(name.to_string(), Entry::Variable(Box::new(read)));
+  }
+
+  // a function called with exactly `arity` arguments, which may shadow a built-in one
+  pub fn define_function(
+    &mut self,
+    name: &str,
+    arity: usize,
+    f: impl Fn(&[N]) -> Result<N> + 'static,
+  ) {
+    assert_ident(name);
+    self.functions.insert(
+      name.to_string(),
+      Native {
+        arity,
+        f: Box::new(f),
+      },
+    );
+  }
+
+  pub fn is_defined(&self, name: &str) -> bool {
+    self.values.contains_key(name)
+  }
+
+  pub fn has_function(&self, name: &str) -> bool {
+    self.functions.contains_key(name)
+  }
+
+  pub fn value(&self, name: &str) -> Option<N> {
+    self.values.get(name).map(|entry| match entry {
+      Entry::Constant(n) => n.clone(),
+      Entry::Variable(read) => read(),
+    })
+  }
+
+  // `None` when the host defines no function of the name
+  pub fn call(&self, name: &str, args: &[N]) -> Option<Result<N>> {
+    let native = self.functions.get(name)?;
+    if args.len() != native.arity {
+      return Some(Err(InterpreterErrorKind::ArityMismatch {
+        name: name.to_string(),
+        expected: native.arity,
+        found: args.len(),
+      }));
+    }
+    Some((native.f)(args))
+  }
+}
+
+fn assert_ident(name: &str) {
+  assert!(
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+      && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_'),
+    "'{}' cannot be used as a name",
+    name
+  );
+}
@@ -0,0 +1,401 @@
+// symbolic differentiation of an `Ast` by one of its variables, with the rules of sums,
+// products, quotients and powers, and the chain rule through the built-in functions. the
+// nodes of a derivative span the node of the input they are the derivative of
+
+use super::ast::{Ast, AstKind, BinOp, BinOpKind, UniOp, UniOpKind};
+use super::bignum::BigInt;
+use super::builtins::Func;
+use super::interpreter::{Interpreter, InterpreterError, InterpreterErrorKind};
+use super::number::Number;
+use super::Loc;
+use std::collections::HashSet;
+
+type Result<T> = std::result::Result<T, InterpreterError>;
+
+impl<N: Number> Interpreter<N> {
+  // the derivative of `expr` by the variable `x`, simplified as `simplify` does
+  pub fn diff(&self, expr: &Ast, x: &str) -> Result<Ast> {
+    self.derivative(expr, x).map(|d| self.simplify(&d))
+  }
+
+  // walks the tree rather than recursing, however deep it is. a node is checked on the way
+  // down, so the first error is that of the outermost node which cannot be differentiated,
+  // and the rules apply on the way up, to the derivatives of its children
+  fn derivative(&self, expr: &Ast, x: &str) -> Result<Ast> {
+    let constants = Constants::of(expr, x);
+    expr.walk(
+      |node| {
+        let fail = |e| Err(InterpreterError::new(e, node.loc.clone()));
+        match node.value {
+          AstKind::Error => fail(InterpreterErrorKind::SyntaxError),
+          AstKind::Assign { .. } => fail(InterpreterErrorKind::NotDifferentiable("=".to_string())),
+          _ if constants.has(node) => Ok(Some(At(node.loc.clone()).num(0))),
+          AstKind::Call { ref name, ref args } => self
+            .differentiable(name, args.len())
+            .map_or_else(fail, |_| Ok(None)),
+          _ => Ok(None),
+        }
+      },
+      |node, derivatives| Ok(self.rule(node, derivatives, &constants)),
+    )
+  }
+
+  // the derivative of a node depending on `x`, from those of its children
+  fn rule(&self, expr: &Ast, mut derivatives: Vec<Ast>, constants: &Constants) -> Ast {
+    let at = At(expr.loc.clone());
+    match expr.value {
+      AstKind::Var(_) => at.num(1),
+      AstKind::UniOp { ref op, .. } => {
+        let de = derivatives.pop().unwrap();
+        match op.value {
+          UniOpKind::Plus => de,
+          UniOpKind::Minus => at.neg(de),
+        }
+      }
+      AstKind::BinOp {
+        ref op,
+        ref l,
+        ref r,
+      } => {
+        let dv = derivatives.pop().unwrap();
+        let du = derivatives.pop().unwrap();
+        let constant = (constants.has(l), constants.has(r));
+        at.binop(
+          &op.value,
+          l.as_ref().clone(),
+          r.as_ref().clone(),
+          du,
+          dv,
+          constant,
+        )
+      }
+      AstKind::Call { ref name, ref args } => {
+        let f = self.differentiable(name, args.len()).unwrap();
+        at.chain(f, args, derivatives, constants)
+      }
+      _ => unreachable!("{:?} is constant", expr.value),
+    }
+  }
+
+  // the function of a call to differentiate by the chain rule
+  fn differentiable(
+    &self,
+    name: &str,
+    argc: usize,
+  ) -> std::result::Result<Func, InterpreterErrorKind> {
+    // a function of the host's is differentiated no more than an unknown one
+    let f: Func = match name.parse() {
+      Ok(f) if !self.defines_function(name) => f,
+      _ => return Err(InterpreterErrorKind::NotDifferentiable(name.to_string())),
+    };
+    if argc != f.arity() {
+      return Err(InterpreterErrorKind::ArityMismatch {
+        name: name.to_string(),
+        expected: f.arity(),
+        found: argc,
+      });
+    }
+    match f {
+      Func::Floor | Func::Ceil | Func::Fact | Func::Min | Func::Max => {
+        Err(InterpreterErrorKind::NotDifferentiable(name.to_string()))
+      }
+      f => Ok(f),
+    }
+  }
+}
+
+// the nodes of a tree which do not depend on `x`, found all at once from the leaves up. one
+// which cannot be evaluated, for its syntax error or assignment, does not, to be reported
+// as such
+struct Constants(HashSet<*const Ast>);
+
+impl Constants {
+  fn of(expr: &Ast, x: &str) -> Self {
+    let mut constants = HashSet::new();
+    expr.fold(|node, children: Vec<bool>| {
+      let constant = match node.value {
+        AstKind::Num(_) | AstKind::Float(_) => true,
+        AstKind::Var(ref name) => name != x,
+        AstKind::Assign { .. } | AstKind::Error => false,
+        _ => children.iter().all(|&c| c),
+      };
+      if constant {
+        constants.insert(node as *const Ast);
+      }
+      constant
+    });
+    Constants(constants)
+  }
+
+  fn has(&self, node: &Ast) -> bool {
+    self.0.contains(&(node as *const Ast))
+  }
+}
+
+fn is_int(ast: &Ast, n: u64) -> bool {
+  match ast.value {
+    AstKind::Num(ref m) => m.to_i64() == Some(n as i64),
+    _ => false,
+  }
+}
+
+// builds the nodes of a derivative at one place, leaving out the terms the rules give
+// which are zero, and the factors which are one
+struct At(Loc);
+
+impl At {
+  fn num(&self, n: u64) -> Ast {
+    Ast::num(n, self.0.clone())
+  }
+
+  fn call(&self, f: Func, arg: Ast) -> Ast {
+    Ast::call(f.name(), vec![arg], self.0.clone())
+  }
+
+  fn neg(&self, mut e: Ast) -> Ast {
+    if is_int(&e, 0) {
+      return e;
+    }
+    // `--e` is `e` whatever `e` is, even with the variables undefined
+    if let AstKind::UniOp {
+      ref op,
+      e: ref mut inner,
+    } = e.value
+    {
+      if op.value == UniOpKind::Minus {
+        return std::mem::replace(inner, self.num(0));
+      }
+    }
+    Ast::uniop(UniOp::minus(self.0.clone()), e, self.0.clone())
+  }
+
+  fn add(&self, l: Ast, r: Ast) -> Ast {
+    match (is_int(&l, 0), is_int(&r, 0)) {
+      (true, _) => r,
+      (_, true) => l,
+      _ => Ast::binop(BinOp::add(self.0.clone()), l, r, self.0.clone()),
+    }
+  }
+
+  fn sub(&self, l: Ast, r: Ast) -> Ast {
+    match (is_int(&l, 0), is_int(&r, 0)) {
+      (_, true) => l,
+      (true, _) => self.neg(r),
+      _ => Ast::binop(BinOp::sub(self.0.clone()), l, r, self.0.clone()),
+    }
+  }
+
+  fn mul(&self, l: Ast, r: Ast) -> Ast {
+    if is_int(&l, 0) || is_int(&r, 0) {
+      return self.num(0);
+    }
+    match (is_int(&l, 1), is_int(&r, 1)) {
+      (true, _) => r,
+      (_, true) => l,
+      _ => Ast::binop(BinOp::mult(self.0.clone()), l, r, self.0.clone()),
+    }
+  }
+
+  fn div(&self, l: Ast, r: Ast) -> Ast {
+    if is_int(&l, 0) || is_int(&r, 1) {
+      return l;
+    }
+    Ast::binop(BinOp::div(self.0.clone()), l, r, self.0.clone())
+  }
+
+  // the derivative of `u op v`, given those of `u` and `v` and which of them is constant
+  fn binop(&self, op: &BinOpKind, u: Ast, v: Ast, du: Ast, dv: Ast, constant: (bool, bool)) -> Ast {
+    match op {
+      BinOpKind::Add => self.add(du, dv),
+      BinOpKind::Sub => self.sub(du, dv),
+      BinOpKind::Mult => self.add(self.mul(du, v), self.mul(u, dv)),
+      BinOpKind::Div if constant.1 => self.div(du, v),
+      BinOpKind::Div => self.div(
+        self.sub(self.mul(du, v.clone()), self.mul(u, dv)),
+        self.pow(v, self.num(2)),
+      ),
+      BinOpKind::Pow => self.power(u, v, du, dv, constant.0, constant.1),
+    }
+  }
+
+  // the derivative of a call by the chain rule, given those of its arguments
+  fn chain(&self, f: Func, args: &[Ast], mut derivatives: Vec<Ast>, constants: &Constants) -> Ast {
+    let a = args[0].clone();
+    let call = |f: Func, a: Ast| self.call(f, a);
+    let square_of_a = || self.pow(a.clone(), self.num(2));
+    let outer = match f {
+      // the functions of two arguments as expressions of the others
+      Func::Log => {
+        let db = derivatives.pop().unwrap();
+        let da = derivatives.pop().unwrap();
+        let ln = |a: &Ast, da: Ast| {
+          let d = if constants.has(a) {
+            self.num(0)
+          } else {
+            self.mul(self.div(self.num(1), a.clone()), da)
+          };
+          (call(Func::Ln, a.clone()), d)
+        };
+        let ((u, du), (v, dv)) = (ln(&args[0], da), ln(&args[1], db));
+        let constant = (constants.has(&args[0]), constants.has(&args[1]));
+        return self.binop(&BinOpKind::Div, u, v, du, dv, constant);
+      }
+      Func::Pow => {
+        let dv = derivatives.pop().unwrap();
+        let du = derivatives.pop().unwrap();
+        let constant = (constants.has(&args[0]), constants.has(&args[1]));
+        return self.binop(&BinOpKind::Pow, a, args[1].clone(), du, dv, constant);
+      }
+      Func::Atan2 => {
+        let (y, z) = (a, args[1].clone());
+        let dz = derivatives.pop().unwrap();
+        let dy = derivatives.pop().unwrap();
+        let squares = self.add(
+          self.pow(y.clone(), self.num(2)),
+          self.pow(z.clone(), self.num(2)),
+        );
+        return self.div(self.sub(self.mul(z, dy), self.mul(y, dz)), squares);
+      }
+      Func::Floor | Func::Ceil | Func::Fact | Func::Min | Func::Max => {
+        unreachable!("{} is not differentiable", f.name())
+      }
+      Func::Sin => call(Func::Cos, a),
+      Func::Cos => self.neg(call(Func::Sin, a)),
+      Func::Tan => self.div(self.num(1), self.pow(call(Func::Cos, a), self.num(2))),
+      Func::Asin => self.div(
+        self.num(1),
+        call(Func::Sqrt, self.sub(self.num(1), square_of_a())),
+      ),
+      Func::Acos => self.neg(self.div(
+        self.num(1),
+        call(Func::Sqrt, self.sub(self.num(1), square_of_a())),
+      )),
+      Func::Atan => self.div(self.num(1), self.add(self.num(1), square_of_a())),
+      Func::Exp => call(Func::Exp, a),
+      Func::Ln => self.div(self.num(1), a),
+      Func::Log2 => self.div(self.num(1), self.mul(a, call(Func::Ln, self.num(2)))),
+      Func::Log10 => self.div(self.num(1), self.mul(a, call(Func::Ln, self.num(10)))),
+      Func::Sqrt => self.div(self.num(1), self.mul(self.num(2), call(Func::Sqrt, a))),
+      Func::Abs => self.div(a.clone(), call(Func::Abs, a)),
+    };
+    self.mul(outer, derivatives.pop().unwrap())
+  }
+
+  fn pow(&self, l: Ast, r: Ast) -> Ast {
+    if is_int(&r, 1) {
+      return l;
+    }
+    Ast::binop(BinOp::pow(self.0.clone()), l, r, self.0.clone())
+  }
+
+  // the derivative of `u ^ v` where `u` or `v` may be constant
+  fn power(&self, u: Ast, v: Ast, du: Ast, dv: Ast, u_constant: bool, v_constant: bool) -> Ast {
+    let ln_u = || self.call(Func::Ln, u.clone());
+    if v_constant {
+      // an integer exponent stays one, which folding `n - 1` might not in every mode
+      let exponent = match v.value {
+        AstKind::Num(ref n) => Ast::new(AstKind::Num(n - &BigInt::from(1i64)), v.loc.clone()),
+        _ => self.sub(v.clone(), self.num(1)),
+      };
+      let lowered = self.pow(u.clone(), exponent);
+      self.mul(self.mul(v, lowered), du)
+    } else if u_constant {
+      self.mul(self.mul(self.pow(u.clone(), v), ln_u()), dv)
+    } else {
+      let inner = self.add(
+        self.mul(dv, ln_u()),
+        self.div(self.mul(v.clone(), du), u.clone()),
+      );
+      self.mul(self.pow(u, v), inner)
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::super::interpreter::Value;
+  use super::*;
+
+  fn diff(input: &str, x: &str) -> Result<Ast> {
+    Interpreter::<Value>::new().diff(&input.parse().unwrap(), x)
+  }
+
+  fn value(interpreter: &mut Interpreter<f64>, input: &str) -> f64 {
+    interpreter.eval(&input.parse().unwrap()).unwrap()
+  }
+
+  #[test]
+  fn test_diff() {
+    let cases = [
+      ("x ^ 2", "x", "2 * x"),
+      ("3 * x + y", "x", "3"),
+      ("x * y + y ^ 2", "y", "x + 2 * y"),
+      ("sin(x ^ 2)", "x", "cos(x ^ 2) * (2 * x)"),
+      ("-cos(x)", "x", "sin(x)"),
+      ("y - cos(x)", "x", "sin(x)"),
+      ("x / y", "x", "1 / y"),
+      ("exp(y)", "x", "0"),
+    ];
+    for (input, x, expected) in cases.iter() {
+      assert_eq!(diff(input, x).unwrap().to_string(), *expected, "{}", input);
+    }
+
+    assert_eq!(
+      diff("1 + floor(x)", "x"),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::NotDifferentiable("floor".to_string()),
+        Loc(4, 12)
+      ))
+    );
+    assert_eq!(
+      diff("x = 1", "x").unwrap_err().value,
+      InterpreterErrorKind::NotDifferentiable("=".to_string())
+    );
+    // a function of the host's may be anything
+    let mut interpreter = Interpreter::<f64>::new();
+    interpreter
+      .context_mut()
+      .define_function("sin", 1, |args: &[f64]| Ok(args[0]));
+    assert_eq!(
+      interpreter
+        .diff(&"sin(x)".parse().unwrap(), "x")
+        .unwrap_err()
+        .value,
+      InterpreterErrorKind::NotDifferentiable("sin".to_string())
+    );
+  }
+
+  // every derivative agrees with the slope over a small step either side of a point
+  #[test]
+  fn test_diff_numerically() {
+    let inputs = [
+      "3 * x ^ 3 - 2 * x + 7",
+      "x / (1 + x ^ 2)",
+      "2 ^ x + x ^ x",
+      "sin(x) * cos(2 * x) - tan(x / 3)",
+      "asin(x / 2) + acos(x / 3) + atan(x)",
+      "exp(-x ^ 2) + ln(x) + log2(x) + log10(x) + log(x, 3)",
+      "sqrt(1 + x) * abs(x - 3) + pow(x, 2.5) + atan2(x, 2) + atan2(1, x)",
+    ];
+    let mut interpreter = Interpreter::<f64>::new();
+    for input in inputs.iter() {
+      let d = interpreter.diff(&input.parse().unwrap(), "x").unwrap();
+      for &x in [0.3, 1.2, 1.7].iter() {
+        let h = 1e-6;
+        value(&mut interpreter, &format!("x = {:?}", x + h));
+        let right = value(&mut interpreter, input);
+        value(&mut interpreter, &format!("x = {:?}", x - h));
+        let left = value(&mut interpreter, input);
+        value(&mut interpreter, &format!("x = {:?}", x));
+        let slope = interpreter.eval(&d).unwrap();
+        assert!(
+          ((right - left) / (2.0 * h) - slope).abs() < 1e-6 * slope.abs().max(1.0),
+          "{} at {}: {}",
+          input,
+          x,
+          d
+        );
+      }
+    }
+  }
+}
@@ -0,0 +1,376 @@
+// rustc-style reports of errors against the input they were found in
+
+use super::Loc;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Label {
+  loc: Loc,
+  message: String,
+  // the primary label is where the error is, secondary ones say why
+  primary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+  message: String,
+  code: Option<&'static str>,
+  labels: Vec<Label>,
+  helps: Vec<String>,
+  notes: Vec<String>,
+}
+
+// a line and the labels on it with their columns there
+type LineLabels<'a> = (usize, Vec<(usize, usize, &'a Label)>);
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+impl Diagnostic {
+  pub fn error(message: impl Into<String>) -> Self {
+    Diagnostic {
+      message: message.into(),
+      code: None,
+      labels: Vec::new(),
+      helps: Vec::new(),
+      notes: Vec::new(),
+    }
+  }
+
+  // the stable error code, shown as `error[C0201]`
+  pub fn code(mut self, code: &'static str) -> Self {
+    self.code = Some(code);
+    self
+  }
+
+  pub(super) fn primary(self, loc: Loc, message: impl Into<String>) -> Self {
+    self.label(loc, message, true)
+  }
+
+  pub(super) fn secondary(self, loc: Loc, message: impl Into<String>) -> Self {
+    self.label(loc, message, false)
+  }
+
+  fn label(mut self, loc: Loc, message: impl Into<String>, primary: bool) -> Self {
+    self.labels.push(Label {
+      loc,
+      message: message.into(),
+      primary,
+    });
+    self
+  }
+
+  pub fn help(mut self, message: impl Into<String>) -> Self {
+    self.helps.push(message.into());
+    self
+  }
+
+  pub fn note(mut self, message: impl Into<String>) -> Self {
+    self.notes.push(message.into());
+    self
+  }
+
+  // colors are only for terminals, which the REPL checks with `is_terminal`
+  pub fn render(&self, input: &str, color: bool) -> String {
+    let paint = |s: &str, style: &str| {
+      if color && !s.is_empty() {
+        format!("{}{}{}", style, s, RESET)
+      } else {
+        s.to_string()
+      }
+    };
+    let lines = Lines::new(input);
+    let mut out = String::new();
+    let level = match self.code {
+      Some(code) => format!("error[{}]", code),
+      None => "error".to_string(),
+    };
+    writeln!(
+      out,
+      "{}: {}",
+      paint(&level, RED),
+      paint(&self.message, BOLD)
+    )
+    .unwrap();
+
+    // the labels by line, each line in order of column
+    let mut by_line: Vec<LineLabels> = Vec::new();
+    for label in &self.labels {
+      let (line, start, end) = lines.span(&label.loc);
+      match by_line.iter_mut().find(|(l, _)| *l == line) {
+        Some((_, labels)) => labels.push((start, end, label)),
+        None => by_line.push((line, vec![(start, end, label)])),
+      }
+    }
+    by_line.sort_by_key(|(line, _)| *line);
+    for (_, labels) in by_line.iter_mut() {
+      labels.sort_by_key(|&(start, _, _)| start);
+    }
+
+    let width = by_line
+      .last()
+      .map_or(1, |(line, _)| (line + 1).to_string().len());
+    let gutter = " ".repeat(width);
+    let bar = paint("|", BLUE);
+    if let Some(label) = self.labels.iter().find(|l| l.primary) {
+      let (line, col) = lines.line_col(label.loc.0);
+      writeln!(out, "{}{} {}:{}", gutter, paint("-->", BLUE), line, col).unwrap();
+    }
+    if !by_line.is_empty() {
+      writeln!(out, "{} {}", gutter, bar).unwrap();
+    }
+    let mut prev = None;
+    for (line, labels) in &by_line {
+      if prev.is_some_and(|p| p + 1 < *line) {
+        writeln!(out, "{}", paint("...", BLUE)).unwrap();
+      }
+      prev = Some(*line);
+      let number = format!("{:>width$}", line + 1, width = width);
+      writeln!(
+        out,
+        "{} {} {}",
+        paint(&number, BLUE),
+        bar,
+        lines.text(*line)
+      )
+      .unwrap();
+
+      // the underlines, with primary ones drawn over secondary ones
+      let end = labels.iter().map(|&(_, end, _)| end).max().unwrap();
+      let mut marks = vec![' '; end];
+      for primary in [false, true] {
+        for &(start, end, _) in labels.iter().filter(|l| l.2.primary == primary) {
+          let c = if primary { '^' } else { '-' };
+          marks[start..end].iter_mut().for_each(|m| *m = c);
+        }
+      }
+      let mut underline = String::new();
+      for (i, &m) in marks.iter().enumerate() {
+        if m == ' ' {
+          underline.push(m);
+          continue;
+        }
+        let style = match labels.iter().rev().find(|&&(s, e, _)| s <= i && i < e) {
+          Some((_, _, label)) if label.primary => RED,
+          _ => BLUE,
+        };
+        underline += &paint(&m.to_string(), style);
+      }
+
+      // the last label's message goes after the underlines, the others hang below
+      let style = |label: &Label| if label.primary { RED } else { BLUE };
+      let mut pending: Vec<_> = labels.iter().filter(|l| !l.2.message.is_empty()).collect();
+      match pending.last() {
+        Some(&&(_, _, label)) if labels.last().unwrap().2 == label => {
+          underline += " ";
+          underline += &paint(&label.message, style(label));
+          pending.pop();
+        }
+        _ => {}
+      }
+      writeln!(out, "{} {} {}", gutter, bar, underline.trim_end()).unwrap();
+      while let Some(&(start, _, label)) = pending.pop() {
+        let mut row = String::new();
+        let mut col = 0;
+        for &&(s, _, l) in &pending {
+          row += &" ".repeat(s.saturating_sub(col));
+          row += &paint("|", style(l));
+          col = s + 1;
+        }
+        let hang = format!("{}{}", row, " ".repeat(start.saturating_sub(col)));
+        writeln!(
+          out,
+          "{} {} {}{}",
+          gutter,
+          bar,
+          hang,
+          paint("|", style(label))
+        )
+        .unwrap();
+        writeln!(
+          out,
+          "{} {} {}{}",
+          gutter,
+          bar,
+          hang,
+          paint(&label.message, style(label))
+        )
+        .unwrap();
+      }
+    }
+    for (kind, messages) in [("help", &self.helps), ("note", &self.notes)] {
+      for message in messages {
+        writeln!(
+          out,
+          "{} {} {}: {}",
+          gutter,
+          paint("=", BLUE),
+          paint(kind, BOLD),
+          message
+        )
+        .unwrap();
+      }
+    }
+    out
+  }
+}
+
+// the 1-based line and character column of a byte offset
+pub(super) fn line_col(input: &str, pos: usize) -> (usize, usize) {
+  Lines::new(input).line_col(std::cmp::min(pos, input.len()))
+}
+
+// the display columns a span takes on the line it starts on, to line text up under it
+pub(super) fn columns(input: &str, loc: &Loc) -> (usize, usize) {
+  let (_, start, end) = Lines::new(input).span(loc);
+  (start, end)
+}
+
+// the columns a character takes in a terminal: two for the wide East Asian characters
+// and emoji, none for combining marks, and one for everything else
+fn width(c: char) -> usize {
+  match c as u32 {
+    0x0300..=0x036f | 0x200b..=0x200f | 0x20d0..=0x20ff | 0xfe00..=0xfe0f => 0,
+    0x1100..=0x115f
+    | 0x2e80..=0x303e
+    | 0x3041..=0x33ff
+    | 0x3400..=0x4dbf
+    | 0x4e00..=0x9fff
+    | 0xa000..=0xa4cf
+    | 0xac00..=0xd7a3
+    | 0xf900..=0xfaff
+    | 0xfe30..=0xfe4f
+    | 0xff00..=0xff60
+    | 0xffe0..=0xffe6
+    | 0x1f300..=0x1f64f
+    | 0x1f900..=0x1f9ff
+    | 0x20000..=0x3fffd => 2,
+    _ => 1,
+  }
+}
+
+// the lines of the input, to turn byte offsets into lines and columns
+struct Lines<'a> {
+  input: &'a str,
+  starts: Vec<usize>,
+}
+
+impl<'a> Lines<'a> {
+  fn new(input: &'a str) -> Self {
+    let mut starts = vec![0];
+    starts.extend(input.match_indices('\n').map(|(i, _)| i + 1));
+    Lines { input, starts }
+  }
+
+  fn line_of(&self, pos: usize) -> usize {
+    self.starts.partition_point(|&start| start <= pos) - 1
+  }
+
+  fn text(&self, line: usize) -> &'a str {
+    let end = self
+      .starts
+      .get(line + 1)
+      .map_or(self.input.len(), |&next| next - 1);
+    &self.input[self.starts[line]..end]
+  }
+
+  // the 0-based display column of a byte offset on `line`
+  fn column(&self, line: usize, pos: usize) -> usize {
+    self.input[self.starts[line]..pos].chars().map(width).sum()
+  }
+
+  // 1-based, as editors show them. editors count characters, not the columns they take
+  // on a terminal, so this is where one goes to in them
+  fn line_col(&self, pos: usize) -> (usize, usize) {
+    let line = self.line_of(pos);
+    (
+      line + 1,
+      self.input[self.starts[line]..pos].chars().count() + 1,
+    )
+  }
+
+  // the line a span starts on and its display columns there, cut at the end of that line.
+  // an empty span, such as the end of the input, still gets one column
+  fn span(&self, loc: &Loc) -> (usize, usize, usize) {
+    let pos = std::cmp::min(loc.0, self.input.len());
+    let line = self.line_of(pos);
+    let end = std::cmp::min(loc.1, self.starts[line] + self.text(line).len());
+    let start = self.column(line, pos);
+    let end = self.column(line, std::cmp::max(end, pos));
+    (line, start, std::cmp::max(end, start + 1))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_render() {
+    let d = Diagnostic::error("zero division error")
+      .primary(Loc(2, 3), "division by zero")
+      .secondary(Loc(5, 10), "this evaluates to zero");
+    assert_eq!(
+      d.render("1 / (2 - 2)", false),
+      "\
+error: zero division error
+ --> 1:3
+  |
+1 | 1 / (2 - 2)
+  |   ^  ----- this evaluates to zero
+  |   |
+  |   division by zero
+"
+    );
+
+    let d = Diagnostic::error("'(' is not closed")
+      .primary(Loc(8, 9), "unclosed parenthesis")
+      .help("add a ')'");
+    assert_eq!(
+      d.render("1 +\n2 * (3", false),
+      "\
+error: '(' is not closed
+ --> 2:5
+  |
+2 | 2 * (3
+  |     ^ unclosed parenthesis
+  = help: add a ')'
+"
+    );
+
+    // the end of the input has no character to point at, so the caret goes past it
+    let d = Diagnostic::error("End of file")
+      .primary(Loc(3, 4), "expected an expression")
+      .note("a note");
+    assert_eq!(
+      d.render("1 +", false),
+      "\
+error: End of file
+ --> 1:4
+  |
+1 | 1 +
+  |    ^ expected an expression
+  = note: a note
+"
+    );
+
+    // wide characters take two columns, so the carets after them move two per character
+    let d = Diagnostic::error("invalid char '円'").primary(Loc(7, 10), "not part of any token");
+    assert_eq!(
+      d.render("x = 10 円", false),
+      "\
+error: invalid char '円'
+ --> 1:8
+  |
+1 | x = 10 円
+  |        ^^ not part of any token
+"
+    );
+    // while the line and column are counted in characters, as editors count them
+    assert_eq!(line_col("é + 日本", 5), (1, 5));
+    assert_eq!(line_col("é + 日本", 8), (1, 6));
+    assert_eq!(line_col("1\n🙂 + $", 9), (2, 5));
+  }
+}
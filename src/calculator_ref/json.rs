@@ -0,0 +1,137 @@
+// a small JSON writer for the machine-readable output, so it needs no dependency
+
+use super::diagnostic::line_col;
+use super::{Loc, StdError};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+  Int(i64),
+  String(String),
+  Array(Vec<Json>),
+  // the keys in the order they are written
+  Object(Vec<(&'static str, Json)>),
+}
+
+impl fmt::Display for Json {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Json::Int(n) => write!(f, "{}", n),
+      Json::String(s) => write_string(f, s),
+      Json::Array(items) => {
+        write!(f, "[")?;
+        for (i, item) in items.iter().enumerate() {
+          if i > 0 {
+            write!(f, ",")?;
+          }
+          write!(f, "{}", item)?;
+        }
+        write!(f, "]")
+      }
+      Json::Object(fields) => {
+        write!(f, "{{")?;
+        for (i, (key, value)) in fields.iter().enumerate() {
+          if i > 0 {
+            write!(f, ",")?;
+          }
+          write_string(f, key)?;
+          write!(f, ":{}", value)?;
+        }
+        write!(f, "}}")
+      }
+    }
+  }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+  write!(f, "\"")?;
+  for c in s.chars() {
+    match c {
+      '"' => write!(f, "\\\"")?,
+      '\\' => write!(f, "\\\\")?,
+      '\n' => write!(f, "\\n")?,
+      '\r' => write!(f, "\\r")?,
+      '\t' => write!(f, "\\t")?,
+      c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+      c => write!(f, "{}", c)?,
+    }
+  }
+  write!(f, "\"")
+}
+
+fn string(s: impl Into<String>) -> Json {
+  Json::String(s.into())
+}
+
+pub fn result(value: String) -> Json {
+  Json::Object(vec![
+    ("type", string("result")),
+    ("value", Json::String(value)),
+  ])
+}
+
+// what is wrong with a REPL command rather than with an expression, such as an unknown
+// command or a missing argument, which has no place in the input to point at
+pub fn command_error(message: String) -> Json {
+  Json::Object(vec![
+    ("type", string("error")),
+    ("kind", string("Command")),
+    ("message", Json::String(message)),
+  ])
+}
+
+// `kind` names the error variant, and the chain is the messages of `e.source()` and on
+pub(super) fn error(
+  kind: &str,
+  code: &str,
+  message: String,
+  loc: &Loc,
+  input: &str,
+  e: &dyn StdError,
+) -> Json {
+  let (line, column) = line_col(input, loc.0);
+  let mut chain = Vec::new();
+  let mut source = e.source();
+  while let Some(e) = source {
+    chain.push(string(e.to_string()));
+    source = e.source();
+  }
+  Json::Object(vec![
+    ("type", string("error")),
+    ("kind", string(kind)),
+    ("code", string(code)),
+    ("message", Json::String(message)),
+    (
+      "span",
+      Json::Object(vec![
+        ("start", Json::Int(loc.0 as i64)),
+        ("end", Json::Int(loc.1 as i64)),
+      ]),
+    ),
+    ("line", Json::Int(line as i64)),
+    ("column", Json::Int(column as i64)),
+    ("source", Json::Array(chain)),
+  ])
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_json() {
+    assert_eq!(
+      result("7/2".to_string()).to_string(),
+      r#"{"type":"result","value":"7/2"}"#
+    );
+    assert_eq!(
+      Json::Array(vec![Json::Int(-1), string("a\"b\\c\nd\u{1}é")]).to_string(),
+      r#"[-1,"a\"b\\c\nd\u0001é"]"#
+    );
+    assert_eq!(Json::Object(vec![]).to_string(), "{}");
+    assert_eq!(
+      command_error("unknown command ':x'".to_string()).to_string(),
+      r#"{"type":"error","kind":"Command","message":"unknown command ':x'"}"#
+    );
+  }
+}
@@ -0,0 +1,48 @@
+// bounds on the work one input can make the parser and the interpreter do, so that input
+// from anyone, such as a request to a service, gets an error rather than exhausting the
+// stack, the time or the memory of the program running the calculator
+
+use crate::i18n::tr_with;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Limits {
+  // how deeply expressions nest, in parentheses, prefix operators, right operands and
+  // arguments, though not in a chain such as `1 + 2 + 3`. the parser and the passes over
+  // a tree keep stacks of their own, all but the derived `Debug`, so any depth works and
+  // a program evaluating generated expressions can raise this as far as it needs
+  pub depth: usize,
+  // how many sub-expressions one evaluation reduces
+  pub steps: usize,
+  // how large a number of the modes without a fixed size can get, in bits
+  pub bits: u64,
+}
+
+impl Default for Limits {
+  fn default() -> Self {
+    Limits {
+      depth: 256,
+      steps: 1_000_000,
+      // about 20,000 decimal digits
+      bits: 1 << 16,
+    }
+  }
+}
+
+// the limit an evaluation reached, with what it is set to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+  Depth(usize),
+  Steps(usize),
+  Bits(u64),
+}
+
+impl fmt::Display for Limit {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Limit::Depth(n) => write!(f, "{}", tr_with("limit.depth", &[n])),
+      Limit::Steps(n) => write!(f, "{}", tr_with("limit.steps", &[n])),
+      Limit::Bits(n) => write!(f, "{}", tr_with("limit.bits", &[n])),
+    }
+  }
+}
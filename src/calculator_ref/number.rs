@@ -0,0 +1,626 @@
+// numeric backends of the interpreter
+
+use super::ast::AstKind;
+use super::bignum::BigInt;
+use super::builtins::{apply_real, finite, Func};
+use super::complex::Complex;
+use super::interpreter::InterpreterErrorKind;
+use super::rational::Rational;
+use crate::i18n::{tr, tr_with};
+use std::cmp::Ordering;
+use std::fmt;
+
+type Result<T> = std::result::Result<T, InterpreterErrorKind>;
+
+// the values an `Interpreter` computes with.
+// a backend declares how each operation fails by the `InterpreterErrorKind` it returns;
+// operations with a default body are optional and report `Unsupported` unless overridden.
+pub trait Number: Clone + fmt::Debug + fmt::Display {
+  // the name the REPL selects this backend by
+  const NAME: &'static str;
+
+  fn from_integer(n: &BigInt) -> Result<Self>;
+  fn from_float(_x: f64) -> Result<Self> {
+    Err(unsupported::<Self>("float literal"))
+  }
+
+  fn neg(&self) -> Result<Self>;
+  fn add(&self, other: &Self) -> Result<Self>;
+  fn sub(&self, other: &Self) -> Result<Self>;
+  fn mul(&self, other: &Self) -> Result<Self>;
+  fn div(&self, other: &Self) -> Result<Self>;
+
+  // what the real-valued functions of `builtins` work on; without it they are unsupported
+  fn to_f64(&self) -> Option<f64> {
+    None
+  }
+  // a built-in function of one argument, computed in floating point unless overridden
+  fn apply(&self, f: Func) -> Result<Self> {
+    apply_real(f, self)
+  }
+  fn compare(&self, other: &Self) -> Result<Ordering> {
+    match (self.to_f64(), other.to_f64()) {
+      (Some(l), Some(r)) => l
+        .partial_cmp(&r)
+        .ok_or_else(|| InterpreterErrorKind::Domain(tr("domain.nan").to_string())),
+      _ => Err(unsupported::<Self>("comparison")),
+    }
+  }
+  fn pow(&self, exp: &Self) -> Result<Self> {
+    pow_real(self, exp)
+  }
+  // named constants such as `pi`, looked up when no variable has the name
+  fn constant(name: &str) -> Option<Result<Self>> {
+    real_constant(name)
+  }
+
+  // the size of the value in bits, which an interpreter keeps within its limits. values
+  // of a fixed size cannot grow, so they count as none
+  fn bits(&self) -> u64 {
+    0
+  }
+  // at least how many bits `self ^ exp` takes, to refuse a power too large before
+  // computing it
+  fn pow_bits(&self, _exp: &Self) -> u64 {
+    0
+  }
+  // at least how many bits `self!` takes, for the same
+  fn fact_bits(&self) -> u64 {
+    0
+  }
+
+  // how the REPL shows a result, which may say more than `Display` does
+  fn describe(&self) -> String {
+    self.to_string()
+  }
+
+  // the literal evaluating to this value, for the optimizer to fold constants into.
+  // values with none, such as 1/3 or i, stay the expressions computing them
+  fn literal(&self) -> Option<AstKind> {
+    None
+  }
+}
+
+fn float_literal(x: f64) -> Option<AstKind> {
+  if x.is_finite() {
+    Some(AstKind::Float(x))
+  } else {
+    None
+  }
+}
+
+fn unsupported<N: Number>(op: &'static str) -> InterpreterErrorKind {
+  InterpreterErrorKind::Unsupported { op, mode: N::NAME }
+}
+
+fn real_constant<N: Number>(name: &str) -> Option<Result<N>> {
+  match name {
+    "pi" => Some(N::from_float(std::f64::consts::PI)),
+    "e" => Some(N::from_float(std::f64::consts::E)),
+    _ => None,
+  }
+}
+
+fn pow_real<N: Number>(base: &N, exp: &N) -> Result<N> {
+  let (x, y) = match (base.to_f64(), exp.to_f64()) {
+    (Some(x), Some(y)) => (x, y),
+    _ => return Err(unsupported::<N>("pow")),
+  };
+  if x == 0.0 && y < 0.0 {
+    return Err(InterpreterErrorKind::DivisionByZero);
+  }
+  if x < 0.0 && y.fract() != 0.0 {
+    return Err(InterpreterErrorKind::Domain(tr_with(
+      "domain.negative_to_fraction",
+      &[&format!("{:?}", x), &format!("{:?}", y)],
+    )));
+  }
+  N::from_float(finite(x.powf(y))?)
+}
+
+// exponents of exact backends have to fit in a `u32`
+fn small_exponent(exp: &BigInt) -> Result<u32> {
+  match exp.to_i64() {
+    Some(e) if 0 <= e && e <= u32::MAX as i64 => Ok(e as u32),
+    _ => Err(InterpreterErrorKind::Overflow),
+  }
+}
+
+// `x ^ exp` takes at least `(bits - 1) * exp + 1` bits where `x` takes `bits`
+fn power_bits(bits: u64, exp: &BigInt) -> u64 {
+  // the cast saturates, so a huge exponent still compares as larger than any limit
+  (bits.saturating_sub(1) as f64 * BigInt::to_f64(exp).abs()) as u64
+}
+
+// `n! >= (n / e) ^ n`, so it takes at least `n * log2(n / e)` bits
+fn factorial_bits(n: &BigInt) -> u64 {
+  let n = BigInt::to_f64(n);
+  if n < 3.0 {
+    return 0;
+  }
+  // the cast saturates, like that of `power_bits`
+  (n * (n.log2() - std::f64::consts::LOG2_E)) as u64
+}
+
+// `n!` with every digit, for the backends whose integers have no limit. a negative `n` is
+// left to `apply_real` to report
+fn factorial(n: &BigInt) -> Result<BigInt> {
+  if n.is_negative() {
+    return apply_real(Func::Fact, n);
+  }
+  let n = n.to_i64().ok_or(InterpreterErrorKind::Overflow)?;
+  Ok((2..=n).fold(BigInt::from(1u64), |product, i| &product * &BigInt::from(i)))
+}
+
+fn not_an_integer(x: f64) -> InterpreterErrorKind {
+  InterpreterErrorKind::Domain(tr_with("domain.not_integer", &[&format!("{:?}", x)]))
+}
+
+// 64-bit integers which are promoted to floats when mixed with them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+  Int(i64),
+  Float(f64),
+}
+impl Value {
+  fn to_f64(self) -> f64 {
+    match self {
+      Value::Int(n) => n as f64,
+      Value::Float(x) => x,
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Int(n) => n.fmt(f),
+      // `{:?}` keeps the fraction part visible (`3.0`, not `3`)
+      Value::Float(x) => write!(f, "{:?}", x),
+    }
+  }
+}
+
+impl Number for Value {
+  const NAME: &'static str = "machine";
+
+  fn from_integer(n: &BigInt) -> Result<Self> {
+    i64::from_integer(n).map(Value::Int)
+  }
+  fn from_float(x: f64) -> Result<Self> {
+    Ok(Value::Float(x))
+  }
+
+  fn neg(&self) -> Result<Self> {
+    match *self {
+      Value::Int(n) => n.neg().map(Value::Int),
+      Value::Float(x) => Ok(Value::Float(-x)),
+    }
+  }
+  fn add(&self, other: &Self) -> Result<Self> {
+    match (*self, *other) {
+      (Value::Int(l), Value::Int(r)) => l.add(&r).map(Value::Int),
+      (l, r) => l.to_f64().add(&r.to_f64()).map(Value::Float),
+    }
+  }
+  fn sub(&self, other: &Self) -> Result<Self> {
+    match (*self, *other) {
+      (Value::Int(l), Value::Int(r)) => l.sub(&r).map(Value::Int),
+      (l, r) => l.to_f64().sub(&r.to_f64()).map(Value::Float),
+    }
+  }
+  fn mul(&self, other: &Self) -> Result<Self> {
+    match (*self, *other) {
+      (Value::Int(l), Value::Int(r)) => l.mul(&r).map(Value::Int),
+      (l, r) => l.to_f64().mul(&r.to_f64()).map(Value::Float),
+    }
+  }
+  fn div(&self, other: &Self) -> Result<Self> {
+    match (*self, *other) {
+      (Value::Int(l), Value::Int(r)) => l.div(&r).map(Value::Int),
+      (l, r) => l.to_f64().div(&r.to_f64()).map(Value::Float),
+    }
+  }
+
+  fn to_f64(&self) -> Option<f64> {
+    Some(Value::to_f64(*self))
+  }
+  fn apply(&self, f: Func) -> Result<Self> {
+    match (*self, f) {
+      (Value::Int(n), Func::Abs)
+      | (Value::Int(n), Func::Floor)
+      | (Value::Int(n), Func::Ceil)
+      | (Value::Int(n), Func::Fact) => n.apply(f).map(Value::Int),
+      (n, f) => apply_real(f, &n),
+    }
+  }
+  fn compare(&self, other: &Self) -> Result<Ordering> {
+    match (*self, *other) {
+      (Value::Int(l), Value::Int(r)) => Ok(l.cmp(&r)),
+      (l, r) => Value::to_f64(l).compare(&Value::to_f64(r)),
+    }
+  }
+  fn pow(&self, exp: &Self) -> Result<Self> {
+    match (*self, *exp) {
+      (Value::Int(l), Value::Int(r)) if r >= 0 => Number::pow(&l, &r).map(Value::Int),
+      (l, r) => Value::to_f64(l).pow(&Value::to_f64(r)).map(Value::Float),
+    }
+  }
+
+  fn literal(&self) -> Option<AstKind> {
+    match *self {
+      Value::Int(n) => Some(AstKind::Num(BigInt::from(n))),
+      Value::Float(x) => float_literal(x),
+    }
+  }
+}
+
+impl Number for i64 {
+  const NAME: &'static str = "int";
+
+  fn from_integer(n: &BigInt) -> Result<Self> {
+    n.to_i64().ok_or(InterpreterErrorKind::Overflow)
+  }
+  // integral float literals such as `1e3` are still integers
+  fn from_float(x: f64) -> Result<Self> {
+    match Rational::from_f64(x) {
+      Some(q) if q.is_integer() => i64::from_integer(q.numer()),
+      _ => Err(not_an_integer(x)),
+    }
+  }
+
+  fn neg(&self) -> Result<Self> {
+    self.checked_neg().ok_or(InterpreterErrorKind::Overflow)
+  }
+  fn add(&self, other: &Self) -> Result<Self> {
+    self
+      .checked_add(*other)
+      .ok_or(InterpreterErrorKind::Overflow)
+  }
+  fn sub(&self, other: &Self) -> Result<Self> {
+    self
+      .checked_sub(*other)
+      .ok_or(InterpreterErrorKind::Overflow)
+  }
+  fn mul(&self, other: &Self) -> Result<Self> {
+    self
+      .checked_mul(*other)
+      .ok_or(InterpreterErrorKind::Overflow)
+  }
+  fn div(&self, other: &Self) -> Result<Self> {
+    if *other == 0 {
+      return Err(InterpreterErrorKind::DivisionByZero);
+    }
+    self
+      .checked_div(*other)
+      .ok_or(InterpreterErrorKind::Overflow)
+  }
+
+  fn to_f64(&self) -> Option<f64> {
+    Some(*self as f64)
+  }
+  fn apply(&self, f: Func) -> Result<Self> {
+    match f {
+      Func::Abs => self.checked_abs().ok_or(InterpreterErrorKind::Overflow),
+      Func::Floor | Func::Ceil => Ok(*self),
+      Func::Fact if *self >= 0 => (2..=*self)
+        .try_fold(1i64, |product, i| product.checked_mul(i))
+        .ok_or(InterpreterErrorKind::Overflow),
+      _ => apply_real(f, self),
+    }
+  }
+  fn compare(&self, other: &Self) -> Result<Ordering> {
+    Ok(self.cmp(other))
+  }
+  // a negative exponent gives a fraction, which `from_float` rejects unless it is integral
+  fn pow(&self, exp: &Self) -> Result<Self> {
+    if *exp < 0 {
+      return pow_real(self, exp);
+    }
+    let exp = small_exponent(&BigInt::from(*exp))?;
+    self.checked_pow(exp).ok_or(InterpreterErrorKind::Overflow)
+  }
+
+  fn literal(&self) -> Option<AstKind> {
+    Some(AstKind::Num(BigInt::from(*self)))
+  }
+}
+
+impl Number for f64 {
+  const NAME: &'static str = "float";
+
+  fn from_integer(n: &BigInt) -> Result<Self> {
+    Ok(n.to_f64())
+  }
+  fn from_float(x: f64) -> Result<Self> {
+    Ok(x)
+  }
+
+  fn neg(&self) -> Result<Self> {
+    Ok(-self)
+  }
+  fn add(&self, other: &Self) -> Result<Self> {
+    Ok(self + other)
+  }
+  fn sub(&self, other: &Self) -> Result<Self> {
+    Ok(self - other)
+  }
+  fn mul(&self, other: &Self) -> Result<Self> {
+    Ok(self * other)
+  }
+  // a zero divisor is reported rather than giving infinity, like the integer backends
+  fn div(&self, other: &Self) -> Result<Self> {
+    if *other == 0.0 {
+      return Err(InterpreterErrorKind::DivisionByZero);
+    }
+    Ok(self / other)
+  }
+
+  fn to_f64(&self) -> Option<f64> {
+    Some(*self)
+  }
+
+  fn literal(&self) -> Option<AstKind> {
+    float_literal(*self)
+  }
+}
+
+impl Number for BigInt {
+  const NAME: &'static str = "bignum";
+
+  fn from_integer(n: &BigInt) -> Result<Self> {
+    Ok(n.clone())
+  }
+  fn from_float(x: f64) -> Result<Self> {
+    match Rational::from_f64(x) {
+      Some(q) if q.is_integer() => Ok(q.numer().clone()),
+      _ => Err(not_an_integer(x)),
+    }
+  }
+
+  fn neg(&self) -> Result<Self> {
+    Ok(-self.clone())
+  }
+  fn add(&self, other: &Self) -> Result<Self> {
+    Ok(self + other)
+  }
+  fn sub(&self, other: &Self) -> Result<Self> {
+    Ok(self - other)
+  }
+  fn mul(&self, other: &Self) -> Result<Self> {
+    Ok(self * other)
+  }
+  fn div(&self, other: &Self) -> Result<Self> {
+    self
+      .div_rem(other)
+      .map(|(q, _)| q)
+      .ok_or(InterpreterErrorKind::DivisionByZero)
+  }
+
+  fn to_f64(&self) -> Option<f64> {
+    Some(BigInt::to_f64(self))
+  }
+  fn apply(&self, f: Func) -> Result<Self> {
+    match f {
+      Func::Abs => Ok(self.abs()),
+      Func::Floor | Func::Ceil => Ok(self.clone()),
+      Func::Fact => factorial(self),
+      _ => apply_real(f, self),
+    }
+  }
+  fn compare(&self, other: &Self) -> Result<Ordering> {
+    Ok(self.cmp(other))
+  }
+  fn pow(&self, exp: &Self) -> Result<Self> {
+    if exp.is_negative() {
+      return pow_real(self, exp);
+    }
+    Ok(BigInt::pow(self, small_exponent(exp)?))
+  }
+  fn bits(&self) -> u64 {
+    BigInt::bits(self)
+  }
+  fn pow_bits(&self, exp: &Self) -> u64 {
+    if exp.is_negative() {
+      return 0;
+    }
+    power_bits(BigInt::bits(self), exp)
+  }
+  fn fact_bits(&self) -> u64 {
+    factorial_bits(self)
+  }
+
+  fn literal(&self) -> Option<AstKind> {
+    Some(AstKind::Num(self.clone()))
+  }
+}
+
+impl Number for Rational {
+  const NAME: &'static str = "rational";
+
+  fn from_integer(n: &BigInt) -> Result<Self> {
+    Ok(Rational::from(n.clone()))
+  }
+  fn from_float(x: f64) -> Result<Self> {
+    Rational::from_f64(x).ok_or(InterpreterErrorKind::Overflow)
+  }
+
+  fn neg(&self) -> Result<Self> {
+    Ok(-self.clone())
+  }
+  fn add(&self, other: &Self) -> Result<Self> {
+    Ok(self + other)
+  }
+  fn sub(&self, other: &Self) -> Result<Self> {
+    Ok(self - other)
+  }
+  fn mul(&self, other: &Self) -> Result<Self> {
+    Ok(self * other)
+  }
+  fn div(&self, other: &Self) -> Result<Self> {
+    self
+      .checked_div(other)
+      .ok_or(InterpreterErrorKind::DivisionByZero)
+  }
+
+  fn to_f64(&self) -> Option<f64> {
+    Some(Rational::to_f64(self))
+  }
+  fn apply(&self, f: Func) -> Result<Self> {
+    match f {
+      Func::Abs => Ok(self.abs()),
+      Func::Floor => Ok(Rational::from(self.floor())),
+      Func::Ceil => Ok(Rational::from(self.ceil())),
+      Func::Fact if self.is_integer() => factorial(self.numer()).map(Rational::from),
+      _ => apply_real(f, self),
+    }
+  }
+  fn compare(&self, other: &Self) -> Result<Ordering> {
+    Ok(self.cmp(other))
+  }
+  // integral exponents stay exact, others go through floating point
+  fn pow(&self, exp: &Self) -> Result<Self> {
+    if !exp.is_integer() {
+      return pow_real(self, exp);
+    }
+    let e = small_exponent(&exp.numer().abs())?;
+    let p = self.pow(e);
+    if exp.numer().is_negative() {
+      Rational::from(BigInt::from(1u64))
+        .checked_div(&p)
+        .ok_or(InterpreterErrorKind::DivisionByZero)
+    } else {
+      Ok(p)
+    }
+  }
+  fn bits(&self) -> u64 {
+    self.numer().bits() + self.denom().bits()
+  }
+  fn pow_bits(&self, exp: &Self) -> u64 {
+    if !exp.is_integer() {
+      return 0;
+    }
+    power_bits(self.numer().bits(), exp.numer())
+      .saturating_add(power_bits(self.denom().bits(), exp.numer()))
+  }
+  fn fact_bits(&self) -> u64 {
+    if !self.is_integer() {
+      return 0;
+    }
+    factorial_bits(self.numer())
+  }
+
+  // a fraction alone is hard to size up, so show its decimal expansion as well
+  fn describe(&self) -> String {
+    if self.is_integer() {
+      self.to_string()
+    } else {
+      format!("{} ({})", self, self.to_decimal(20))
+    }
+  }
+
+  fn literal(&self) -> Option<AstKind> {
+    if self.is_integer() {
+      Some(AstKind::Num(self.numer().clone()))
+    } else {
+      None
+    }
+  }
+}
+
+impl Number for Complex {
+  const NAME: &'static str = "complex";
+
+  fn from_integer(n: &BigInt) -> Result<Self> {
+    Ok(Complex::new(n.to_f64(), 0.0))
+  }
+  fn from_float(x: f64) -> Result<Self> {
+    Ok(Complex::new(x, 0.0))
+  }
+
+  fn neg(&self) -> Result<Self> {
+    Ok(-*self)
+  }
+  fn add(&self, other: &Self) -> Result<Self> {
+    Ok(*self + *other)
+  }
+  fn sub(&self, other: &Self) -> Result<Self> {
+    Ok(*self - *other)
+  }
+  fn mul(&self, other: &Self) -> Result<Self> {
+    Ok(*self * *other)
+  }
+  fn div(&self, other: &Self) -> Result<Self> {
+    if other.is_zero() {
+      return Err(InterpreterErrorKind::DivisionByZero);
+    }
+    Ok(*self / *other)
+  }
+
+  // only numbers on the real line have a real value
+  fn to_f64(&self) -> Option<f64> {
+    if self.im == 0.0 {
+      Some(self.re)
+    } else {
+      None
+    }
+  }
+  fn apply(&self, f: Func) -> Result<Self> {
+    let z = match f {
+      Func::Sqrt => self.sqrt(),
+      Func::Exp => self.exp(),
+      Func::Ln if self.is_zero() => return apply_real(f, self),
+      Func::Ln => self.ln(),
+      Func::Sin => self.sin(),
+      Func::Cos => self.cos(),
+      Func::Abs => Complex::new(self.norm(), 0.0),
+      _ => return apply_real(f, self),
+    };
+    finite(z.re)?;
+    finite(z.im)?;
+    Ok(z)
+  }
+  fn pow(&self, exp: &Self) -> Result<Self> {
+    if self.is_zero() {
+      return match exp.to_f64() {
+        Some(y) if y < 0.0 => Err(InterpreterErrorKind::DivisionByZero),
+        Some(y) => Ok(if y == 0.0 {
+          Complex::new(1.0, 0.0)
+        } else {
+          *self
+        }),
+        None => Err(InterpreterErrorKind::Domain(
+          tr("domain.zero_to_complex").to_string(),
+        )),
+      };
+    }
+    let z = match exp.to_f64() {
+      Some(y) if y.fract() == 0.0 && y.abs() <= u32::MAX as f64 => {
+        let z = self.powi(y.abs() as u32);
+        if y < 0.0 {
+          Complex::new(1.0, 0.0) / z
+        } else {
+          z
+        }
+      }
+      _ => (*exp * self.ln()).exp(),
+    };
+    finite(z.re)?;
+    finite(z.im)?;
+    Ok(z)
+  }
+  fn constant(name: &str) -> Option<Result<Self>> {
+    match name {
+      "i" => Some(Ok(Complex::new(0.0, 1.0))),
+      _ => real_constant(name),
+    }
+  }
+
+  fn literal(&self) -> Option<AstKind> {
+    if self.im == 0.0 {
+      float_literal(self.re)
+    } else {
+      None
+    }
+  }
+}
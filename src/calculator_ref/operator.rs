@@ -0,0 +1,184 @@
+// the operator table driving the Pratt parser in `ast`
+
+use super::ast::{BinOpKind, UniOpKind};
+use super::lexer::{lex, TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Assoc {
+  Left,
+  Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fixity {
+  Prefix,
+  Infix(Assoc),
+  Postfix,
+}
+
+// what an operator builds once its operands are parsed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+  UniOp(UniOpKind),
+  BinOp(BinOpKind),
+  // a call of the named function with the operands as its arguments
+  Call(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Operator {
+  pub symbol: String,
+  pub fixity: Fixity,
+  // higher binds tighter; `+` is 10, `*` 20, unary `-` 30, `^` 40 and `!` 50
+  pub bp: u32,
+  pub action: Action,
+}
+
+impl Operator {
+  pub fn prefix(symbol: &str, bp: u32, action: Action) -> Self {
+    Self::new(symbol, Fixity::Prefix, bp, action)
+  }
+  pub fn infix(symbol: &str, bp: u32, assoc: Assoc, action: Action) -> Self {
+    Self::new(symbol, Fixity::Infix(assoc), bp, action)
+  }
+  pub fn postfix(symbol: &str, bp: u32, action: Action) -> Self {
+    Self::new(symbol, Fixity::Postfix, bp, action)
+  }
+  fn new(symbol: &str, fixity: Fixity, bp: u32, action: Action) -> Self {
+    Operator {
+      symbol: symbol.to_string(),
+      fixity,
+      bp,
+      action,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorTable {
+  ops: Vec<Operator>,
+}
+
+impl Default for OperatorTable {
+  fn default() -> Self {
+    use self::Action::{BinOp, Call, UniOp};
+    use self::Assoc::*;
+    OperatorTable {
+      ops: vec![
+        Operator::infix("+", 10, Left, BinOp(BinOpKind::Add)),
+        Operator::infix("-", 10, Left, BinOp(BinOpKind::Sub)),
+        Operator::infix("*", 20, Left, BinOp(BinOpKind::Mult)),
+        Operator::infix("/", 20, Left, BinOp(BinOpKind::Div)),
+        Operator::prefix("+", 30, UniOp(UniOpKind::Plus)),
+        Operator::prefix("-", 30, UniOp(UniOpKind::Minus)),
+        Operator::infix("^", 40, Right, BinOp(BinOpKind::Pow)),
+        Operator::infix("**", 40, Right, BinOp(BinOpKind::Pow)),
+        Operator::postfix("!", 50, Call("fact".to_string())),
+      ],
+    }
+  }
+}
+
+impl OperatorTable {
+  // adds an operator, replacing the one with the same symbol in the same position.
+  // a symbol the lexer does not know yet must be made of ASCII punctuation other than
+  // the parentheses, `,` and `=`, so it cannot be confused with numbers or names
+  pub fn register(&mut self, op: Operator) {
+    assert!(
+      !op.symbol.is_empty()
+        && op
+          .symbol
+          .bytes()
+          .all(|b| b.is_ascii_punctuation() && !b"(),=._".contains(&b)),
+      "'{}' cannot be used as an operator symbol",
+      op.symbol
+    );
+    let infix_like = |f: Fixity| f != Fixity::Prefix;
+    self
+      .ops
+      .retain(|o| o.symbol != op.symbol || infix_like(o.fixity) != infix_like(op.fixity));
+    self.ops.push(op);
+  }
+
+  pub fn prefix(&self, token: &TokenKind) -> Option<&Operator> {
+    self.find(token, |f| f == Fixity::Prefix)
+  }
+
+  // infix and postfix operators share a position, right after an operand
+  pub fn infix_or_postfix(&self, token: &TokenKind) -> Option<&Operator> {
+    self.find(token, |f| f != Fixity::Prefix)
+  }
+
+  fn find(&self, token: &TokenKind, position: impl Fn(Fixity) -> bool) -> Option<&Operator> {
+    let symbol = token.symbol()?;
+    self
+      .ops
+      .iter()
+      .find(|op| op.symbol == symbol && position(op.fixity))
+  }
+
+  // the symbols the lexer has to be told about, as it only knows the built-in ones
+  pub fn custom_symbols(&self) -> Vec<&str> {
+    let builtin = |s: &str| match lex(s).as_deref() {
+      Ok([token]) => token.value.symbol() == Some(s),
+      _ => false,
+    };
+    self
+      .ops
+      .iter()
+      .map(|op| op.symbol.as_str())
+      .filter(|s| !builtin(s))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_operator_table() {
+    let mut table = OperatorTable::default();
+    assert_eq!(table.prefix(&TokenKind::Minus).unwrap().bp, 30);
+    assert_eq!(table.infix_or_postfix(&TokenKind::Minus).unwrap().bp, 10);
+    assert_eq!(table.prefix(&TokenKind::Asterisk), None);
+    assert!(table.custom_symbols().is_empty());
+    assert_eq!(
+      table.infix_or_postfix(&TokenKind::Bang).map(|op| op.fixity),
+      Some(Fixity::Postfix)
+    );
+
+    table.register(Operator::postfix(
+      "%",
+      50,
+      Action::Call("percent".to_string()),
+    ));
+    table.register(Operator::infix(
+      "-",
+      15,
+      Assoc::Left,
+      Action::BinOp(BinOpKind::Sub),
+    ));
+    assert_eq!(table.custom_symbols(), vec!["%"]);
+    assert_eq!(table.infix_or_postfix(&TokenKind::Minus).unwrap().bp, 15);
+    assert_eq!(table.prefix(&TokenKind::Minus).unwrap().bp, 30);
+    assert_eq!(
+      table
+        .infix_or_postfix(&TokenKind::Symbol("%".to_string()))
+        .map(|op| op.fixity),
+      Some(Fixity::Postfix)
+    );
+  }
+
+  #[test]
+  #[should_panic]
+  fn test_operator_table_bad_symbol() {
+    let mut table = OperatorTable::default();
+    table.register(Operator::infix(
+      "mod",
+      20,
+      Assoc::Left,
+      Action::Call("mod".to_string()),
+    ));
+  }
+}
@@ -0,0 +1,235 @@
+// simplification of an `Ast` for an interpreter: constant subtrees are folded into
+// literals, and identities such as `x * 1` and `--x` drop what does not change the value.
+// every node keeps the span it had in the source, so errors still point at the input.
+//
+// an error is never simplified away. a constant subtree failing to evaluate, such as
+// `1 / 0` even where its value is not needed, is kept to fail at run time, and an identity
+// dropping an operand or an operation which could fail, such as `x - x` or `--x` with
+// 64-bit integers, only applies when it evaluates without error in the interpreter now
+
+use super::ast::{alike, Ast, AstKind, BinOpKind, UniOpKind};
+use super::bignum::BigInt;
+use super::interpreter::{Interpreter, InterpreterErrorKind};
+use super::number::Number;
+
+impl<N: Number> Interpreter<N> {
+  // simplifies the tree from the leaves up, without recursing however deep it is. each node
+  // comes up with the value it has now, so no subtree is evaluated more than once
+  pub fn simplify(&self, expr: &Ast) -> Ast {
+    expr
+      .fold(|node, children| self.simplify_node(node, children))
+      .0
+  }
+
+  // a node simplified, given its children simplified with their values now
+  fn simplify_node(&self, expr: &Ast, children: Vec<(Ast, Option<N>)>) -> (Ast, Option<N>) {
+    let value = self.value_now(expr, children.iter().map(|(_, n)| n.clone()).collect());
+    let loc = expr.loc.clone();
+    let mut children = children.into_iter();
+    let mut next = || children.next().unwrap();
+    let ast = match expr.value {
+      AstKind::Assign { ref name, .. } => Ast::assign(name.clone(), next().0, loc),
+      AstKind::Call { ref name, .. } => {
+        Ast::call(name, children.map(|(arg, _)| arg).collect(), loc)
+      }
+      AstKind::UniOp { ref op, .. } => {
+        let (e, e_value) = next();
+        match (&op.value, &e.value) {
+          (UniOpKind::Plus, _) => return (e, e_value),
+          (UniOpKind::Minus, AstKind::UniOp { op: inner, e: x })
+            if inner.value == UniOpKind::Minus && e_value.is_some() =>
+          {
+            return (x.as_ref().clone(), value);
+          }
+          _ => Ast::uniop(op.clone(), e, loc),
+        }
+      }
+      AstKind::BinOp { ref op, .. } => {
+        let (l, r) = (next(), next());
+        match op.value {
+          BinOpKind::Add if is_int(&r.0, 0) => return l,
+          BinOpKind::Add if is_int(&l.0, 0) => return r,
+          BinOpKind::Sub if is_int(&r.0, 0) => return l,
+          BinOpKind::Mult | BinOpKind::Div if is_int(&r.0, 1) => return l,
+          BinOpKind::Mult if is_int(&l.0, 1) => return r,
+          BinOpKind::Pow if is_int(&r.0, 1) => return l,
+          _ => {}
+        }
+        let ast = Ast::binop(op.clone(), l.0, r.0, loc);
+        if let AstKind::BinOp { ref l, ref r, .. } = ast.value {
+          if op.value == BinOpKind::Sub && alike(&l.value, &r.value, false) {
+            if let Some(zero) = value.as_ref().and_then(|n| n.literal()) {
+              return (Ast::new(zero, ast.loc), value);
+            }
+          }
+        }
+        ast
+      }
+      _ => return (expr.clone(), value),
+    };
+    // a node whose operands are all literals now may be one itself
+    match self.constant(&ast, &value).and_then(|n| n.literal()) {
+      Some(literal) => (Ast::new(literal, ast.loc), value),
+      None => (ast, value),
+    }
+  }
+
+  // the value of a call or an operation on literals, if computing it cannot fail. calls of
+  // functions the host defines are never folded, as they may not give the same every time,
+  // and neither is a value past the interpreter's limits, which evaluating it would report
+  fn constant(&self, ast: &Ast, value: &Option<N>) -> Option<N> {
+    let literal = |n: &N| n.literal().is_some();
+    match ast.value {
+      AstKind::Call { ref name, ref args } if !self.defines_function(name) => {
+        let args = args
+          .iter()
+          .map(|arg| self.literal_value(arg))
+          .collect::<Option<Vec<_>>>()?;
+        self.within_limits(self.call(name, &args)).filter(literal)
+      }
+      AstKind::UniOp { ref e, .. } if self.literal_value(e).is_some() => value.clone(),
+      AstKind::BinOp { ref l, ref r, .. }
+        if self.literal_value(l).is_some() && self.literal_value(r).is_some() =>
+      {
+        value.clone()
+      }
+      _ => None,
+    }
+  }
+
+  fn literal_value(&self, ast: &Ast) -> Option<N> {
+    match ast.value {
+      AstKind::Num(ref n) => N::from_integer(n).ok(),
+      AstKind::Float(x) => N::from_float(x).ok(),
+      _ => None,
+    }
+  }
+
+  // the value a node of literals, variables and operators has in the interpreter as it is
+  // now, if it has one without an error, given those of its operands
+  fn value_now(&self, ast: &Ast, operands: Vec<Option<N>>) -> Option<N> {
+    let mut operands = operands.into_iter();
+    let mut next = || operands.next().unwrap();
+    match ast.value {
+      AstKind::Num(_) | AstKind::Float(_) => self.literal_value(ast),
+      AstKind::Var(ref name) => self.load(name).ok(),
+      AstKind::UniOp { ref op, .. } => self.within_limits(op.value.apply(next()?)),
+      AstKind::BinOp { ref op, .. } => {
+        let (l, r) = (next()?, next()?);
+        self.within_limits(self.apply_binop(&op.value, l, r))
+      }
+      _ => None,
+    }
+  }
+
+  // a value computed without an error, and no larger than the limits let a value be. a
+  // power past them is refused before it is computed
+  fn within_limits(&self, n: Result<N, InterpreterErrorKind>) -> Option<N> {
+    n.ok().filter(|n| self.fits(n).is_ok())
+  }
+}
+
+// an integer literal, as `1.0` would turn an integer result into a float one
+fn is_int(ast: &Ast, n: i64) -> bool {
+  match ast.value {
+    AstKind::Num(ref m) => *m == BigInt::from(n),
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::super::interpreter::Value;
+  use super::super::Loc;
+  use super::*;
+
+  fn simplified<N: Number>(interpreter: &Interpreter<N>, input: &str) -> Ast {
+    interpreter.simplify(&input.parse().unwrap())
+  }
+
+  #[test]
+  fn test_simplify() {
+    let mut interpreter = Interpreter::<Value>::new();
+    let cases = [
+      ("1 + 2 * 3", "7"),
+      ("x * (2 - 1)", "x"),
+      ("1 * x + 0", "x"),
+      ("0 + x / 1 - 0", "x"),
+      ("+x", "x"),
+      ("x ^ (3 - 2)", "x"),
+      ("x * 1.0", "x * 1.0"),
+      ("sqrt(16) + y", "4.0 + y"),
+      ("max(x, 2 ^ 3)", "max(x, 8)"),
+      ("y = 2 * 3", "y = 6"),
+      ("1 - 3 - x", "-2 - x"),
+      ("x ^ (1 - 3)", "x ^ (-2)"),
+      // the errors are still there to be reported
+      ("(1 / 0) * 0 + x", "1 / 0 * 0 + x"),
+      ("sqrt(-1) * 1", "sqrt(-1)"),
+      ("9223372036854775807 + 1", "9223372036854775807 + 1"),
+      ("x - x", "x - x"),
+      ("--x", "--x"),
+    ];
+    for (input, expected) in cases.iter() {
+      assert_eq!(
+        simplified(&interpreter, input).to_string(),
+        *expected,
+        "{}",
+        input
+      );
+    }
+
+    // with `x` defined the identities cannot hide an undefined variable
+    interpreter.eval(&"x = 5".parse().unwrap()).unwrap();
+    assert_eq!(simplified(&interpreter, "x - x + y").to_string(), "y");
+    assert_eq!(simplified(&interpreter, "--x * y").to_string(), "x * y");
+
+    // but not an overflow on negating the smallest integer
+    let mut interpreter = Interpreter::<i64>::new();
+    interpreter
+      .eval(&"x = -9223372036854775807 - 1".parse().unwrap())
+      .unwrap();
+    assert_eq!(simplified(&interpreter, "--x").to_string(), "--x");
+
+    // nor a call of the host's function, which may not give the same every time
+    let mut interpreter = Interpreter::<Value>::new();
+    interpreter
+      .context_mut()
+      .define_function("sin", 1, |args: &[Value]| Ok(args[0]));
+    assert_eq!(simplified(&interpreter, "sin(1 + 1)").to_string(), "sin(2)");
+
+    // nor a value past the limits, which is refused rather than computed
+    let interpreter = Interpreter::<BigInt>::new();
+    for input in ["2 ^ 100000000 + x", "pow(3, 100000000) * x"] {
+      assert_eq!(simplified(&interpreter, input).to_string(), input);
+    }
+    assert_eq!(
+      simplified(&interpreter, "2 ^ 100 + x").to_string(),
+      "1267650600228229401496703205376 + x"
+    );
+  }
+
+  #[test]
+  fn test_simplify_loc() {
+    let interpreter = Interpreter::<Value>::new();
+    // a folded node spans what it replaced, and a kept one where it was
+    let ast = simplified(&interpreter, "y + (2 * 3) * 1");
+    match ast.value {
+      AstKind::BinOp { ref l, ref r, .. } => {
+        assert_eq!(l.loc, Loc(0, 1));
+        assert_eq!(r.value, AstKind::Num(BigInt::from(6i64)));
+        assert_eq!(r.loc, Loc(5, 10));
+      }
+      _ => panic!("{:?}", ast),
+    }
+    assert_eq!(ast.loc, Loc(0, 15));
+
+    // so an error after simplifying is reported at the same place
+    let input = "1 + 2 * 3 + 4 / (1 - 1)";
+    let ast: Ast = input.parse().unwrap();
+    let expected = Interpreter::<Value>::new().eval(&ast);
+    let actual = Interpreter::<Value>::new().eval(&interpreter.simplify(&ast));
+    assert_eq!(actual, expected);
+    assert_eq!(actual.unwrap_err().loc, Loc(12, 22));
+  }
+}
@@ -0,0 +1,221 @@
+// exact fractions for the rational mode of the interpreter
+
+use super::bignum::BigInt;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// always in lowest terms with a positive denominator, so the derived equality is exact
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+  num: BigInt,
+  den: BigInt,
+}
+
+impl Rational {
+  // `None` when `den` is zero
+  pub fn new(num: BigInt, den: BigInt) -> Option<Self> {
+    if den.is_zero() {
+      return None;
+    }
+    let g = num.gcd(&den);
+    let (mut num, mut den) = (num.div_rem(&g).unwrap().0, den.div_rem(&g).unwrap().0);
+    if den.is_negative() {
+      num = -num;
+      den = -den;
+    }
+    Some(Rational { num, den })
+  }
+
+  pub fn numer(&self) -> &BigInt {
+    &self.num
+  }
+
+  pub fn denom(&self) -> &BigInt {
+    &self.den
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.num.is_zero()
+  }
+
+  pub fn is_integer(&self) -> bool {
+    self.den == BigInt::from(1u64)
+  }
+
+  // floats are taken as the shortest decimal that prints back to the same float,
+  // so `0.1` becomes 1/10 rather than the binary fraction closest to it
+  pub fn from_f64(x: f64) -> Option<Self> {
+    if !x.is_finite() {
+      return None;
+    }
+    let s = format!("{:e}", x);
+    let (mantissa, exp) = s.split_at(s.find('e').unwrap());
+    let exp: i32 = exp[1..].parse().unwrap();
+    let (digits, exp) = match mantissa.find('.') {
+      Some(dot) => (
+        mantissa.replace('.', ""),
+        exp - (mantissa.len() - dot - 1) as i32,
+      ),
+      None => (mantissa.to_string(), exp),
+    };
+    let digits: BigInt = digits.parse().unwrap();
+    let scale = BigInt::from(10u64).pow(exp.unsigned_abs());
+    if exp < 0 {
+      Rational::new(digits, scale)
+    } else {
+      Some(Rational::from(&digits * &scale))
+    }
+  }
+
+  pub fn abs(&self) -> Rational {
+    Rational {
+      num: self.num.abs(),
+      den: self.den.clone(),
+    }
+  }
+
+  pub fn floor(&self) -> BigInt {
+    let (q, r) = self.num.div_rem(&self.den).unwrap();
+    // the quotient is truncated toward zero, which is one too large for negative fractions
+    if r.is_negative() {
+      &q - &BigInt::from(1u64)
+    } else {
+      q
+    }
+  }
+
+  pub fn ceil(&self) -> BigInt {
+    -(-self.clone()).floor()
+  }
+
+  pub fn pow(&self, exp: u32) -> Rational {
+    // lowest terms stay lowest terms when both parts are raised to the same power
+    Rational {
+      num: self.num.pow(exp),
+      den: self.den.pow(exp),
+    }
+  }
+
+  pub fn to_f64(&self) -> f64 {
+    self.num.to_f64() / self.den.to_f64()
+  }
+
+  // `None` when `other` is zero
+  pub fn checked_div(&self, other: &Rational) -> Option<Rational> {
+    Rational::new(&self.num * &other.den, &self.den * &other.num)
+  }
+
+  // the decimal expansion cut after `digits` fractional digits, marked with `...` when inexact
+  pub fn to_decimal(&self, digits: u32) -> String {
+    let scaled = &self.num.abs() * &BigInt::from(10u64).pow(digits);
+    let (q, r) = scaled.div_rem(&self.den).unwrap();
+    let q = format!("{:0>width$}", q, width = digits as usize + 1);
+    let (int, frac) = q.split_at(q.len() - digits as usize);
+    let frac = frac.trim_end_matches('0');
+    let sign = if self.num.is_negative() { "-" } else { "" };
+    let dot = if frac.is_empty() { "" } else { "." };
+    let rest = if r.is_zero() { "" } else { "..." };
+    format!("{}{}{}{}{}", sign, int, dot, frac, rest)
+  }
+}
+
+impl From<BigInt> for Rational {
+  fn from(n: BigInt) -> Self {
+    Rational {
+      num: n,
+      den: BigInt::from(1u64),
+    }
+  }
+}
+
+// denominators are positive, so cross-multiplying keeps the order
+impl Ord for Rational {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (&self.num * &other.den).cmp(&(&other.num * &self.den))
+  }
+}
+
+impl PartialOrd for Rational {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Neg for Rational {
+  type Output = Rational;
+  fn neg(self) -> Rational {
+    Rational {
+      num: -self.num,
+      den: self.den,
+    }
+  }
+}
+
+impl<'a> Add<&'a Rational> for &'a Rational {
+  type Output = Rational;
+  fn add(self, other: &Rational) -> Rational {
+    let num = &(&self.num * &other.den) + &(&other.num * &self.den);
+    Rational::new(num, &self.den * &other.den).unwrap()
+  }
+}
+
+impl<'a> Sub<&'a Rational> for &'a Rational {
+  type Output = Rational;
+  fn sub(self, other: &Rational) -> Rational {
+    self + &-other.clone()
+  }
+}
+
+impl<'a> Mul<&'a Rational> for &'a Rational {
+  type Output = Rational;
+  fn mul(self, other: &Rational) -> Rational {
+    Rational::new(&self.num * &other.num, &self.den * &other.den).unwrap()
+  }
+}
+
+impl fmt::Display for Rational {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.is_integer() {
+      self.num.fmt(f)
+    } else {
+      write!(f, "{}/{}", self.num, self.den)
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn ratio(num: i64, den: i64) -> Rational {
+    Rational::new(BigInt::from(num), BigInt::from(den)).unwrap()
+  }
+
+  #[test]
+  fn test_arithmetic() {
+    assert_eq!(ratio(2, -4), ratio(-1, 2));
+    assert_eq!(&ratio(1, 3) + &ratio(1, 6), ratio(1, 2));
+    assert_eq!(&ratio(1, 3) - &ratio(1, 2), ratio(-1, 6));
+    assert_eq!(&ratio(2, 3) * &ratio(9, 4), ratio(3, 2));
+    assert_eq!(ratio(7, 1).checked_div(&ratio(2, 1)), Some(ratio(7, 2)));
+    assert_eq!(ratio(7, 1).checked_div(&ratio(0, 1)), None);
+    assert_eq!(ratio(-7, 2).floor(), BigInt::from(-4i64));
+    assert_eq!(ratio(-7, 2).ceil(), BigInt::from(-3i64));
+    assert_eq!(ratio(-2, 3).pow(3), ratio(-8, 27));
+    assert!(ratio(-1, 2) < ratio(-1, 3));
+  }
+
+  #[test]
+  fn test_conversion() {
+    assert_eq!(Rational::from_f64(0.1), Some(ratio(1, 10)));
+    assert_eq!(Rational::from_f64(-2.5e-3), Some(ratio(-1, 400)));
+    assert_eq!(Rational::from_f64(6.02e3), Some(ratio(6020, 1)));
+    assert_eq!(Rational::from_f64(f64::NAN), None);
+    assert_eq!(ratio(7, 2).to_string(), "7/2");
+    assert_eq!(ratio(-4, 2).to_string(), "-2");
+    assert_eq!(ratio(7, 2).to_decimal(10), "3.5");
+    assert_eq!(ratio(-1, 3).to_decimal(5), "-0.33333...");
+    assert_eq!(ratio(1, 400).to_decimal(5), "0.0025");
+  }
+}
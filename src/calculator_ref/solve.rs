@@ -0,0 +1,448 @@
+// numeric root finding: the value of a variable which makes an expression zero, by
+// Newton's method with the derivative `diff` gives, falling back to bisection where Newton's
+// steps go astray. the search is in floats, so modes without them cannot solve
+
+use super::ast::{Ast, AstKind};
+use super::interpreter::{Interpreter, InterpreterError, InterpreterErrorKind};
+use super::limits::Limits;
+use super::number::Number;
+use super::{Error, Loc};
+use std::fmt;
+
+type Result<T> = std::result::Result<T, InterpreterError>;
+
+// the most steps either method takes before giving up
+const STEPS: usize = 200;
+// how close, relative to the size of the root, two steps are when it is found
+const TOLERANCE: f64 = 1e-12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Start {
+  // a point near the root
+  Guess(f64),
+  // the ends of an interval with the root in it, where the expression has opposite signs
+  Bracket(f64, f64),
+}
+
+impl fmt::Display for Start {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Start::Guess(t) => write!(f, "{:?}", t),
+      Start::Bracket(a, b) => write!(f, "[{:?}, {:?}]", a, b),
+    }
+  }
+}
+
+// `lhs = rhs` as `lhs - rhs`, or an expression, as the first argument of `solve` is parsed
+pub fn equation(input: &str) -> std::result::Result<Ast, Error> {
+  Ast::parse_equation(input, &Limits::default())
+}
+
+impl<N: Number> Interpreter<N> {
+  // the `x` which makes `f` zero, remembered as `ans`. `x` keeps the value it had before
+  pub fn solve(&mut self, f: &Ast, x: &str, start: Start) -> Result<N> {
+    // the whole search is one evaluation as far as the limits go
+    self.restart();
+    let root = self.root(f, x, start)?;
+    self.store("ans", root.clone());
+    Ok(root)
+  }
+
+  // `solve(equation, x, guess)` or `solve(equation, x, from, to)` `depth` levels into the
+  // expression evaluated, whose steps the search takes. the equation nests a level deeper than the
+  // call, and is only evaluated at the points the search tries
+  pub(super) fn solve_call(&mut self, call: &Ast, depth: usize) -> Result<N> {
+    let args = match call.value {
+      AstKind::Call { ref args, .. } => args,
+      _ => unreachable!("{} is not a call", call),
+    };
+    if args.len() != 3 && args.len() != 4 {
+      return Err(InterpreterError::new(
+        InterpreterErrorKind::ArityMismatch {
+          name: "solve".to_string(),
+          expected: 3,
+          found: args.len(),
+        },
+        call.loc.clone(),
+      ));
+    }
+    let x = match args[1].value {
+      AstKind::Var(ref x) => x,
+      _ => {
+        return Err(InterpreterError::new(
+          InterpreterErrorKind::NotVariable(args[1].to_string()),
+          args[1].loc.clone(),
+        ))
+      }
+    };
+    self.deeper(depth + 1, |interpreter| {
+      let start = interpreter.start(&args[2..])?;
+      interpreter.root(&args[0], x, start)
+    })
+  }
+
+  // the start of a search from the values of one or two expressions
+  pub fn start(&mut self, points: &[Ast]) -> Result<Start> {
+    if points.is_empty() || points.len() > 2 {
+      let loc = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => first.loc.merge(&last.loc),
+        _ => Loc(0, 0),
+      };
+      return Err(InterpreterError::new(
+        InterpreterErrorKind::ArityMismatch {
+          name: "solve".to_string(),
+          expected: 3,
+          found: points.len() + 2,
+        },
+        loc,
+      ));
+    }
+    let mut values = Vec::with_capacity(points.len());
+    for point in points {
+      let fail = |e| InterpreterError::new(e, point.loc.clone());
+      let n = self.untraced(|interpreter| interpreter.eval_ast(point))?;
+      values.push(n.to_f64().ok_or_else(|| fail(unsupported::<N>()))?);
+    }
+    Ok(match values[..] {
+      [a, b] => Start::Bracket(a, b),
+      _ => Start::Guess(values[0]),
+    })
+  }
+
+  fn root(&mut self, f: &Ast, x: &str, start: Start) -> Result<N> {
+    let fail = |e| InterpreterError::new(e, f.loc.clone());
+    self.assignable(x).map_err(fail)?;
+    // the search goes through points between the integers
+    N::from_float(0.5).map_err(|_| fail(unsupported::<N>()))?;
+    let saved = self.replace(x, None);
+    let root = self.untraced(|interpreter| interpreter.find_root(f, x, start));
+    self.replace(x, saved);
+    N::from_float(root?).map_err(fail)
+  }
+
+  fn find_root(&mut self, f: &Ast, x: &str, start: Start) -> Result<f64> {
+    let fail = |e| Err(InterpreterError::new(e, f.loc.clone()));
+    let not_found = || fail(InterpreterErrorKind::NoConvergence(start.to_string()));
+    // without a derivative the slope is measured instead
+    let df = self.diff(f, x).ok();
+    match start {
+      Start::Bracket(a, b) => {
+        let (fa, fb) = (self.value_at(f, x, a)?, self.value_at(f, x, b)?);
+        if fa == 0.0 {
+          return Ok(a);
+        } else if fb == 0.0 {
+          return Ok(b);
+        } else if fa.is_sign_negative() == fb.is_sign_negative() {
+          return fail(InterpreterErrorKind::NoSignChange {
+            from: format!("{:?}", a),
+            to: format!("{:?}", b),
+          });
+        }
+        match self.bisect(f, df.as_ref(), x, (a, b), (fa, fb))? {
+          Some(root) => Ok(root),
+          None => not_found(),
+        }
+      }
+      Start::Guess(t) => {
+        // an error where the search starts is the caller's to see
+        self.value_at(f, x, t)?;
+        if let Some(root) = self.newton(f, df.as_ref(), x, t) {
+          return Ok(root);
+        }
+        let (bracket, ends) = match self.bracket_around(f, x, t) {
+          Some(around) => around,
+          None => return not_found(),
+        };
+        match self.bisect(f, df.as_ref(), x, bracket, ends)? {
+          Some(root) => Ok(root),
+          None => not_found(),
+        }
+      }
+    }
+  }
+
+  // Newton's method alone, which is fast near a root but can wander off or cycle
+  // elsewhere. `None` if it does not settle, leaving the errors on the way to bisection
+  fn newton(&mut self, f: &Ast, df: Option<&Ast>, x: &str, mut t: f64) -> Option<f64> {
+    for _ in 0..STEPS {
+      let y = self.value_at(f, x, t).ok()?;
+      if y == 0.0 {
+        return Some(t);
+      }
+      let next = t - y / self.slope_at(f, df, x, t).ok()?;
+      if !next.is_finite() {
+        return None;
+      }
+      if close(next, t) {
+        return Some(next).filter(|&root| self.value_at(f, x, root).is_ok());
+      }
+      t = next;
+    }
+    None
+  }
+
+  // Newton's steps kept inside an interval with a sign change, halving it instead where a
+  // step would leave it or not shrink it fast enough, so it always closes in on a point
+  // where the sign changes. `None` if that is a pole or a jump rather than a root
+  fn bisect(
+    &mut self,
+    f: &Ast,
+    df: Option<&Ast>,
+    x: &str,
+    (mut a, mut b): (f64, f64),
+    (fa, fb): (f64, f64),
+  ) -> Result<Option<f64>> {
+    let smallest = fa.abs().min(fb.abs());
+    let (mut t, mut step) = ((a + b) / 2.0, (b - a).abs());
+    for _ in 0..STEPS {
+      let y = self.value_at(f, x, t)?;
+      if y == 0.0 {
+        return Ok(Some(t));
+      }
+      if y.is_sign_negative() == fa.is_sign_negative() {
+        a = t;
+      } else {
+        b = t;
+      }
+      let newton = self
+        .slope_at(f, df, x, t)
+        .ok()
+        .map(|slope| t - y / slope)
+        .filter(|&next| (next - t).abs() < step / 2.0 && a.min(b) < next && next < a.max(b));
+      let next = newton.unwrap_or((a + b) / 2.0);
+      step = (next - t).abs();
+      if close(next, t) || close(a, b) {
+        let y = self.value_at(f, x, next)?;
+        return Ok(Some(next).filter(|_| y.abs() < smallest));
+      }
+      t = next;
+    }
+    Ok(None)
+  }
+
+  // an interval with a sign change on either side of `t`, widened until there is one
+  fn bracket_around(&mut self, f: &Ast, x: &str, t: f64) -> Option<((f64, f64), (f64, f64))> {
+    let y = self.value_at(f, x, t).ok()?;
+    let mut width = 1e-2 * t.abs().max(1.0);
+    for _ in 0..64 {
+      for &end in [t - width, t + width].iter() {
+        // points where the expression is undefined are passed over
+        if let Ok(fe) = self.value_at(f, x, end) {
+          if fe.is_sign_negative() != y.is_sign_negative() {
+            return Some(((t, end), (y, fe)));
+          }
+        }
+      }
+      width *= 2.0;
+    }
+    None
+  }
+
+  fn value_at(&mut self, f: &Ast, x: &str, t: f64) -> Result<f64> {
+    let fail = |e| InterpreterError::new(e, f.loc.clone());
+    self.replace(x, Some(N::from_float(t).map_err(fail)?));
+    let n = self.eval_ast(f)?;
+    let y = n.to_f64().ok_or_else(|| fail(unsupported::<N>()))?;
+    if y.is_nan() {
+      return Err(fail(InterpreterErrorKind::NoConvergence(format!(
+        "{:?}",
+        t
+      ))));
+    }
+    Ok(y)
+  }
+
+  fn slope_at(&mut self, f: &Ast, df: Option<&Ast>, x: &str, t: f64) -> Result<f64> {
+    let slope = match df {
+      Some(df) => self.value_at(df, x, t)?,
+      None => {
+        let h = 1e-7 * t.abs().max(1.0);
+        (self.value_at(f, x, t + h)? - self.value_at(f, x, t - h)?) / (2.0 * h)
+      }
+    };
+    if slope == 0.0 || !slope.is_finite() {
+      return Err(InterpreterError::new(
+        InterpreterErrorKind::NoConvergence(format!("{:?}", t)),
+        f.loc.clone(),
+      ));
+    }
+    Ok(slope)
+  }
+}
+
+fn close(a: f64, b: f64) -> bool {
+  (a - b).abs() <= TOLERANCE * a.abs().max(1.0)
+}
+
+fn unsupported<N: Number>() -> InterpreterErrorKind {
+  InterpreterErrorKind::Unsupported {
+    op: "solve",
+    mode: N::NAME,
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::super::interpreter::Value;
+  use super::*;
+
+  fn solve(input: &str, x: &str, start: Start) -> Result<f64> {
+    let mut interpreter = Interpreter::<f64>::new();
+    interpreter.solve(&equation(input).unwrap(), x, start)
+  }
+
+  #[test]
+  fn test_equation() {
+    let ast = equation("x ^ 2 = 2 * x").unwrap();
+    assert_eq!(ast.to_string(), "x ^ 2 - 2 * x");
+    assert_eq!(ast.loc, Loc(0, 13));
+    match ast.value {
+      super::super::ast::AstKind::BinOp { ref op, ref r, .. } => {
+        assert_eq!(op.loc, Loc(6, 7));
+        assert_eq!(r.loc, Loc(8, 13));
+      }
+      _ => panic!("{:?}", ast),
+    }
+    assert!(equation("x ^ 2 = ").is_err());
+  }
+
+  #[test]
+  fn test_solve() {
+    let sqrt2 = std::f64::consts::SQRT_2;
+    let close = |r: Result<f64>, expected: f64| (r.unwrap() - expected).abs() < 1e-9;
+    assert!(close(solve("x ^ 2 - 2 = 0", "x", Start::Guess(1.0)), sqrt2));
+    assert!(close(solve("x ^ 2 = 2", "x", Start::Guess(-1.0)), -sqrt2));
+    assert!(close(
+      solve("x ^ 2 = 2", "x", Start::Bracket(0.0, 3.0)),
+      sqrt2
+    ));
+    assert!(close(
+      solve("cos(x) = x", "x", Start::Guess(0.0)),
+      0.7390851332151607
+    ));
+    // Newton's method cycles from 0 here, and the bisection finds it
+    assert!(close(
+      solve("x ^ 3 - 2 * x + 2", "x", Start::Guess(0.0)),
+      -1.7692923542386314
+    ));
+    // no derivative, but a sign change
+    assert!(close(
+      solve("floor(x) - 2.5 + x", "x", Start::Guess(0.0)),
+      1.5
+    ));
+
+    let loc = Loc(0, 9);
+    assert_eq!(
+      solve("x ^ 2 = 2", "x", Start::Bracket(2.0, 3.0)),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::NoSignChange {
+          from: "2.0".to_string(),
+          to: "3.0".to_string()
+        },
+        loc.clone()
+      ))
+    );
+    assert_eq!(
+      solve("x ^ 2 = -1", "x", Start::Guess(1.0)),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::NoConvergence("1.0".to_string()),
+        Loc(0, 10)
+      ))
+    );
+    // a sign change at a pole is not a root
+    assert_eq!(
+      solve("1 / x", "x", Start::Bracket(-1.0, 2.0))
+        .unwrap_err()
+        .value,
+      InterpreterErrorKind::NoConvergence("[-1.0, 2.0]".to_string())
+    );
+    assert_eq!(
+      solve("ln(x) = 1", "x", Start::Guess(-1.0))
+        .unwrap_err()
+        .value
+        .code(),
+      "C0204"
+    );
+  }
+
+  #[test]
+  fn test_solve_call() {
+    let mut interpreter = Interpreter::<Value>::new();
+    let mut eval = |input: &str| interpreter.eval(&input.parse().unwrap());
+    let root = |r: Result<Value>| match r {
+      Ok(Value::Float(t)) => t,
+      r => panic!("{:?}", r),
+    };
+    let sqrt2 = std::f64::consts::SQRT_2;
+    eval("x = 10").unwrap();
+    assert!((root(eval("solve(x^2 - 2 = 0, x, 1)")) - sqrt2).abs() < 1e-9);
+    assert!((root(eval("solve(x ^ 2 = 2, x, -3, 0)")) + sqrt2).abs() < 1e-9);
+    assert!((root(eval("1 + solve(x = cos(x), x, 0)")) - 1.7390851332151607).abs() < 1e-9);
+    // `x` is put back
+    assert_eq!(eval("x"), Ok(Value::Int(10)));
+
+    // the equation is the first argument's alone
+    let ast: Ast = "solve(x = 2 * y, x, y = 1)".parse().unwrap();
+    assert_eq!(ast.to_string(), "solve(x - 2 * y, x, y = 1)");
+    assert!("max(x = y = 2)".parse::<Ast>().is_ok());
+    assert!("solve(x = y = 2, x, 1)".parse::<Ast>().is_err());
+
+    assert_eq!(
+      eval("solve(x ^ 2 = 2, x)"),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::ArityMismatch {
+          name: "solve".to_string(),
+          expected: 3,
+          found: 2
+        },
+        Loc(0, 19)
+      ))
+    );
+    assert_eq!(
+      eval("solve(x ^ 2 = 2, 2 * x, 1)"),
+      Err(InterpreterError::new(
+        InterpreterErrorKind::NotVariable("2 * x".to_string()),
+        Loc(17, 22)
+      ))
+    );
+    assert_eq!(
+      eval("solve(x ^ 2 = -1, x, 1)").unwrap_err().value,
+      InterpreterErrorKind::NoConvergence("1.0".to_string())
+    );
+    // and a search from no point is an error rather than a panic
+    assert_eq!(
+      interpreter.start(&[]).unwrap_err().value,
+      InterpreterErrorKind::ArityMismatch {
+        name: "solve".to_string(),
+        expected: 3,
+        found: 2
+      }
+    );
+  }
+
+  #[test]
+  fn test_solve_state() {
+    let mut interpreter = Interpreter::<Value>::new();
+    interpreter.eval(&"x = 10".parse().unwrap()).unwrap();
+    let f = equation("x ^ 2 = 9").unwrap();
+    let root = interpreter.solve(&f, "x", Start::Guess(1.0)).unwrap();
+    assert_eq!(root, Value::Float(3.0));
+    // `x` is put back, and the root kept as `ans`
+    assert_eq!(
+      interpreter.eval(&"x + ans".parse().unwrap()),
+      Ok(Value::Float(13.0))
+    );
+
+    let mut interpreter = Interpreter::<i64>::new();
+    assert_eq!(
+      interpreter
+        .solve(&f, "x", Start::Guess(1.0))
+        .unwrap_err()
+        .value,
+      InterpreterErrorKind::Unsupported {
+        op: "solve",
+        mode: "int"
+      }
+    );
+  }
+}
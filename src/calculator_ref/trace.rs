@@ -0,0 +1,206 @@
+// the reductions `Interpreter::eval` records in trace mode, shown as the input rewritten
+// with the value of each sub-expression in its place in turn, as `1 + 2 * 3`, `1 + 6`, `7`,
+// or as the input with the value of every sub-expression written under it
+
+use super::diagnostic::columns;
+use super::interpreter::Step;
+use super::Loc;
+
+// the input after each step, starting from the input itself. a step which looks the same
+// written out, such as `-3` reducing to `-3`, is left out
+pub fn render<T: ToString>(input: &str, steps: &[Step<T>]) -> Vec<String> {
+  let mut texts = vec![input.trim().to_string()];
+  for i in 0..steps.len() {
+    let text = rewrite(input, &steps[..=i]);
+    if texts.last() != Some(&text) {
+      texts.push(text);
+    }
+  }
+  texts
+}
+
+// `input` with the outermost of the reduced sub-expressions replaced by their values. any
+// step inside another comes before it, and is covered by it
+fn rewrite<T: ToString>(input: &str, steps: &[Step<T>]) -> String {
+  let mut outermost: Vec<_> = steps
+    .iter()
+    .map(|step| (widen(input, step.loc.0, step.loc.1), &step.value))
+    .collect();
+  outermost.sort_by_key(|&((start, end), _)| (start, std::cmp::Reverse(end)));
+  let (mut text, mut end) = (String::new(), 0);
+  for ((start, to), value) in outermost {
+    if start < end {
+      continue;
+    }
+    text.push_str(&input[end..start]);
+    text.push_str(&value.to_string());
+    end = to;
+  }
+  text.push_str(&input[end..]);
+  text.trim().to_string()
+}
+
+// the input over rows of underlines, one under each sub-expression with its value after
+// it, and each below the values of its operands:
+//
+//   1 + 2 * 3 - x
+//       ----- 6 - 5
+//   --------- 7
+//   ------------- 2
+pub fn annotate<T: ToString>(input: &str, steps: &[Step<T>]) -> String {
+  let mut rows: Vec<Vec<char>> = Vec::new();
+  let mut placed: Vec<(&Loc, usize)> = Vec::new();
+  for step in steps {
+    let (from, to) = widen(input, step.loc.0, step.loc.1);
+    let (start, end) = columns(input, &Loc(from, to));
+    let label: Vec<char> = format!("{} {}", "-".repeat(end - start), step.value.to_string())
+      .chars()
+      .collect();
+    let below = placed
+      .iter()
+      .filter(|(loc, _)| step.loc.0 <= loc.0 && loc.1 <= step.loc.1)
+      .map(|&(_, row)| row + 1)
+      .max()
+      .unwrap_or(0);
+    // the first row from there with room for the label and a space either side of it
+    let free = |row: &Vec<char>| {
+      let from = start.saturating_sub(1);
+      row
+        .iter()
+        .skip(from)
+        .take(start + label.len() + 1 - from)
+        .all(|&c| c == ' ')
+    };
+    let row = (below..rows.len())
+      .find(|&row| free(&rows[row]))
+      .unwrap_or_else(|| {
+        rows.push(Vec::new());
+        rows.len() - 1
+      });
+    let text = &mut rows[row];
+    if text.len() < start + label.len() {
+      text.resize(start + label.len(), ' ');
+    }
+    text[start..start + label.len()].copy_from_slice(&label);
+    placed.push((&step.loc, row));
+  }
+  let mut out = input.trim_end().to_string();
+  for row in rows {
+    out.push('\n');
+    out.extend(row);
+  }
+  out
+}
+
+// a span with the parentheses it has only one of widened to the other. an operation spans
+// its operands, so that of `(1 + 2) * 3` starts inside the parentheses
+fn widen(input: &str, mut start: usize, mut end: usize) -> (usize, usize) {
+  loop {
+    let (mut open, mut close) = (0, 0);
+    for c in input[start..end].chars() {
+      match c {
+        '(' => open += 1,
+        ')' if open > 0 => open -= 1,
+        ')' => close += 1,
+        _ => {}
+      }
+    }
+    match (close, open) {
+      (0, 0) => return (start, end),
+      (0, _) => match input[end..].find(')') {
+        Some(i) => end += i + 1,
+        None => return (start, end),
+      },
+      _ => match input[..start].rfind('(') {
+        Some(i) => start = i,
+        None => return (start, end),
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::super::ast::Ast;
+  use super::super::interpreter::{Interpreter, Value};
+  use super::super::number::Number;
+  use super::super::Loc;
+  use super::*;
+
+  fn trace(interpreter: &mut Interpreter, input: &str) -> String {
+    let _ = interpreter.eval(&input.parse::<Ast>().unwrap());
+    let steps: Vec<_> = interpreter
+      .trace()
+      .iter()
+      .map(|step| Step::new(step.value.describe(), step.loc.clone()))
+      .collect();
+    render(input, &steps).join(" → ")
+  }
+
+  #[test]
+  fn test_trace() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_trace(true);
+    assert_eq!(
+      trace(&mut interpreter, "1 + 2 * 3"),
+      "1 + 2 * 3 → 1 + 6 → 7"
+    );
+    assert_eq!(
+      interpreter.trace(),
+      &[
+        Step::new(Value::Int(6), Loc(4, 9)),
+        Step::new(Value::Int(7), Loc(0, 9))
+      ]
+    );
+    assert_eq!(
+      trace(&mut interpreter, "x = (1 + 2) * -3"),
+      "x = (1 + 2) * -3 → x = (3) * -3 → x = -9 → -9"
+    );
+    assert_eq!(
+      trace(&mut interpreter, "max(x, 2 ^ 3) + sqrt(16)"),
+      "max(x, 2 ^ 3) + sqrt(16) → max(-9, 2 ^ 3) + sqrt(16) → max(-9, 8) + sqrt(16) \
+       → 8 + sqrt(16) → 8 + 4.0 → 12.0"
+    );
+    // up to the error
+    assert_eq!(
+      trace(&mut interpreter, "1 + 2 + 3 / (2 - 2)"),
+      "1 + 2 + 3 / (2 - 2) → 3 + 3 / (2 - 2) → 3 + 3 / (0)"
+    );
+
+    interpreter.set_trace(false);
+    interpreter.eval(&"1 + 2".parse().unwrap()).unwrap();
+    assert_eq!(interpreter.trace(), &[]);
+  }
+
+  #[test]
+  fn test_annotate() {
+    let mut interpreter = Interpreter::<Value>::new();
+    interpreter.set_trace(true);
+    let mut annotate = |input: &str| {
+      let _ = interpreter.eval(&input.parse().unwrap());
+      super::annotate(input, interpreter.trace())
+    };
+    annotate("x = 4");
+    assert_eq!(
+      annotate("1 + 2 * 3 - x"),
+      "\
+1 + 2 * 3 - x
+    ----- 6 - 4
+--------- 7
+------------- 3"
+    );
+    // operands which do not fit beside each other go a row down, above what they make
+    assert_eq!(
+      annotate("max(x, 2 ^ 10) / (x - 2)"),
+      "\
+max(x, 2 ^ 10) / (x - 2)
+    - 4           - 4
+       ------ 1024
+-------------- 1024
+                  ----- 2
+------------------------ 512"
+    );
+    // up to the error
+    assert_eq!(annotate("x + 1 / 0"), "x + 1 / 0\n- 4");
+  }
+}
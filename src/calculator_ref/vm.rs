@@ -0,0 +1,379 @@
+// a compiler from `Ast` to a flat bytecode, and the stack machine running it. for a formula
+// evaluated many times, such as once per plotted point, the tree is walked only once and
+// every run is a loop over the ops. the results and errors are those of `Interpreter::eval`
+
+use super::ast::{Ast, AstKind, BinOpKind, UniOpKind};
+use super::builtins::Func;
+use super::interpreter::{nesting, Interpreter, InterpreterError, InterpreterErrorKind};
+use super::number::Number;
+use super::Loc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+  // pushes a literal, or fails if the mode cannot represent it
+  Const(usize),
+  Load(usize),
+  // fails if the name cannot be assigned to, checked before the value is computed
+  Guard(usize),
+  // assigns the top of the stack, leaving it there
+  Store(usize),
+  UniOp(UniOpKind),
+  BinOp(BinOpKind),
+  // calls the named function with as many values from the top of the stack
+  Call(usize, usize),
+  // `Call` of a name the built-in functions have, unless the host defines it at run time
+  CallBuiltin(Func, usize),
+  // evaluates a call of `solve` by walking its equation, as its arguments are not values to
+  // compute first, but an equation, a variable and where to start. a `solve` the host
+  // defines at run time is called with its arguments evaluated instead
+  Solve(usize),
+  Fail(InterpreterErrorKind),
+}
+
+#[derive(Debug, Clone)]
+pub struct Code<N> {
+  ops: Vec<Op>,
+  // where each op came from, for its errors
+  locs: Vec<Loc>,
+  // the nodes in the order the tree walker takes a step into each, with how deeply they
+  // nest and where they are, and how many of them it has entered by each op, so that both
+  // count the same steps and fail on the same limits at the same places
+  nodes: Vec<(usize, Loc)>,
+  entered: Vec<usize>,
+  consts: Vec<Result<N, InterpreterErrorKind>>,
+  names: Vec<String>,
+  // the calls of `solve`, with their depths
+  solves: Vec<(Ast, usize)>,
+  // the most values on the stack at once
+  depth: usize,
+}
+
+impl<N: Number> Code<N> {
+  pub fn compile(expr: &Ast) -> Self {
+    let mut code = Code {
+      ops: Vec::new(),
+      locs: Vec::new(),
+      nodes: Vec::new(),
+      entered: Vec::new(),
+      consts: Vec::new(),
+      names: Vec::new(),
+      solves: Vec::new(),
+      depth: 0,
+    };
+    code.compile_ast(expr);
+    let mut height = 0;
+    for op in &code.ops {
+      height += effect(op);
+      code.depth = std::cmp::max(code.depth, height as usize);
+    }
+    code
+  }
+
+  pub fn ops(&self) -> &[Op] {
+    &self.ops
+  }
+
+  fn emit(&mut self, op: Op, loc: &Loc) {
+    self.ops.push(op);
+    self.locs.push(loc.clone());
+    self.entered.push(self.nodes.len());
+  }
+
+  fn constant(&mut self, n: Result<N, InterpreterErrorKind>, loc: &Loc) {
+    self.consts.push(n);
+    self.emit(Op::Const(self.consts.len() - 1), loc);
+  }
+
+  fn name(&mut self, name: &str) -> usize {
+    match self.names.iter().position(|n| n == name) {
+      Some(i) => i,
+      None => {
+        self.names.push(name.to_string());
+        self.names.len() - 1
+      }
+    }
+  }
+
+  // operands come before their operator, in the order the tree walker evaluates them. the
+  // tree is walked with a stack of its own, each node on it once to enter and once to leave
+  fn compile_ast(&mut self, expr: &Ast) {
+    let mut stack = vec![(expr, 0, false)];
+    while let Some((expr, depth, left)) = stack.pop() {
+      if left {
+        self.compile_node(expr);
+        continue;
+      }
+      self.nodes.push((depth, expr.loc.clone()));
+      if let AstKind::Call { ref name, .. } = expr.value {
+        if name == "solve" {
+          self.solves.push((expr.clone(), depth));
+          self.emit(Op::Solve(self.solves.len() - 1), &expr.loc);
+          continue;
+        }
+      }
+      if let AstKind::Assign { ref name, .. } = expr.value {
+        let i = self.name(name);
+        self.emit(Op::Guard(i), &expr.loc);
+      }
+      stack.push((expr, depth, true));
+      let children = expr.value.children().into_iter().enumerate().rev();
+      stack.extend(children.map(|(i, e)| (e, depth + nesting(expr, i), false)));
+    }
+  }
+
+  // the op of a node, once its operands are compiled
+  fn compile_node(&mut self, expr: &Ast) {
+    let loc = &expr.loc;
+    match expr.value {
+      AstKind::Num(ref n) => self.constant(N::from_integer(n), loc),
+      AstKind::Float(x) => self.constant(N::from_float(x), loc),
+      AstKind::Var(ref name) => {
+        let i = self.name(name);
+        self.emit(Op::Load(i), loc);
+      }
+      AstKind::Assign { ref name, .. } => {
+        let i = self.name(name);
+        self.emit(Op::Store(i), loc);
+      }
+      AstKind::Call { ref name, ref args } => {
+        let op = match name.parse() {
+          Ok(f) => Op::CallBuiltin(f, args.len()),
+          Err(()) => Op::Call(self.name(name), args.len()),
+        };
+        self.emit(op, loc);
+      }
+      AstKind::Error => self.emit(Op::Fail(InterpreterErrorKind::SyntaxError), loc),
+      AstKind::UniOp { ref op, .. } => self.emit(Op::UniOp(op.value.clone()), loc),
+      AstKind::BinOp { ref op, .. } => self.emit(Op::BinOp(op.value.clone()), loc),
+    }
+  }
+}
+
+// how many values an op leaves on the stack more than it takes
+fn effect(op: &Op) -> isize {
+  match *op {
+    Op::Const(_) | Op::Load(_) | Op::Solve(_) => 1,
+    Op::Guard(_) | Op::Store(_) | Op::UniOp(_) | Op::Fail(_) => 0,
+    Op::BinOp(_) => -1,
+    Op::Call(_, argc) | Op::CallBuiltin(_, argc) => 1 - argc as isize,
+  }
+}
+
+impl<N: Number> Interpreter<N> {
+  // runs compiled code as `eval` evaluates the tree, remembering the result as `ans`
+  pub fn run(&mut self, code: &Code<N>) -> Result<N, InterpreterError> {
+    let mut stack: Vec<N> = Vec::with_capacity(code.depth);
+    self.clear_trace();
+    self.restart();
+    let mut nodes = 0;
+    for ((op, loc), &entered) in code.ops.iter().zip(&code.locs).zip(&code.entered) {
+      // the steps into the nodes up to this op, as the tree walker takes them
+      for (depth, loc) in &code.nodes[nodes..entered] {
+        self
+          .enter(*depth)
+          .map_err(|e| InterpreterError::new(e, loc.clone()))?;
+      }
+      nodes = entered;
+      let fail = |e| InterpreterError::new(e, loc.clone());
+      let n = match *op {
+        Op::Const(i) => match code.consts[i] {
+          // a literal is checked against the limits, but no step of the trace
+          Ok(ref n) => {
+            self.fits(n).map_err(fail)?;
+            stack.push(n.clone());
+            continue;
+          }
+          Err(ref e) => return Err(fail(e.clone())),
+        },
+        Op::Load(i) => self.load(&code.names[i]).map_err(fail)?,
+        Op::Guard(i) => {
+          self.assignable(&code.names[i]).map_err(fail)?;
+          continue;
+        }
+        Op::Store(i) => {
+          let n = stack.pop().unwrap();
+          self.store(&code.names[i], n.clone());
+          n
+        }
+        Op::UniOp(ref op) => {
+          let n = stack.pop().unwrap();
+          op.apply(n).map_err(fail)?
+        }
+        Op::BinOp(ref op) => {
+          let r = stack.pop().unwrap();
+          let l = stack.pop().unwrap();
+          self.apply_binop(op, l, r).map_err(fail)?
+        }
+        Op::Call(i, argc) => {
+          let base = stack.len() - argc;
+          let n = self.call(&code.names[i], &stack[base..]).map_err(fail)?;
+          stack.truncate(base);
+          n
+        }
+        Op::CallBuiltin(f, argc) => {
+          let base = stack.len() - argc;
+          let n = self.call_builtin(f, &stack[base..]).map_err(fail)?;
+          stack.truncate(base);
+          n
+        }
+        Op::Fail(ref e) => return Err(fail(e.clone())),
+        Op::Solve(i) => {
+          let (ref call, depth) = code.solves[i];
+          if self.solves(call) {
+            self.solve_call(call, depth)?
+          } else {
+            self.host_solve(call, depth)?
+          }
+        }
+      };
+      self.fits(&n).map_err(fail)?;
+      self.record(&n, loc);
+      stack.push(n);
+    }
+    let n = stack.pop().unwrap();
+    self.store("ans", n.clone());
+    Ok(n)
+  }
+
+  // a call of `solve` when the host defines a function of its own by that name, decided at
+  // run time as for the built-in functions. its arguments are values like any others, and
+  // are evaluated a level deeper than the call, as the tree walker does
+  fn host_solve(&mut self, call: &Ast, depth: usize) -> Result<N, InterpreterError> {
+    let args = call.value.children();
+    let values = self.deeper(depth + 1, |interpreter| {
+      args
+        .into_iter()
+        .map(|arg| interpreter.eval_ast(arg))
+        .collect::<Result<Vec<_>, _>>()
+    })?;
+    self
+      .call("solve", &values)
+      .map_err(|e| InterpreterError::new(e, call.loc.clone()))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::super::bignum::BigInt;
+  use super::super::interpreter::Value;
+  use super::super::limits::Limits;
+  use super::super::operator::OperatorTable;
+  use super::*;
+
+  // every input gives the same result or error from both, and the same trace, state
+  // carrying over between them
+  fn check<N: Number>(inputs: &[&str], limits: Limits) {
+    let mut tree = Interpreter::<N>::new();
+    let mut vm = Interpreter::<N>::new();
+    for interpreter in [&mut tree, &mut vm] {
+      interpreter.set_limits(limits);
+      interpreter.set_trace(true);
+    }
+    let steps = |interpreter: &Interpreter<N>| {
+      let trace = interpreter.trace().iter();
+      trace
+        .map(|step| (step.value.describe(), step.loc.clone()))
+        .collect::<Vec<_>>()
+    };
+    for input in inputs {
+      let (ast, _) = Ast::parse_partial(input, &OperatorTable::default());
+      let expected = tree.eval(&ast).map(|n| n.describe());
+      let actual = vm.run(&Code::compile(&ast)).map(|n| n.describe());
+      assert_eq!(actual, expected, "{}", input);
+      assert_eq!(steps(&vm), steps(&tree), "{}", input);
+    }
+  }
+
+  #[test]
+  fn test_vm() {
+    let inputs = [
+      "x = 2",
+      "x * 3 + ans",
+      "-x ^ 2 - 1.5",
+      "max(x, 7) / 2",
+      "1 / (x - 2) + y",
+      "y + 1 / (x - 2)",
+      "max(1)",
+      "nope(1)",
+      "sqrt(-1)",
+      "2 ^ 64",
+      "2 ^ 100000000",
+      "18446744073709551616 + 1 / 0",
+      "pi * (1 + ",
+      "z = z + 1",
+      "ans",
+      "solve(x ^ 2 = 2, x, 1) + x",
+      "solve(x ^ 2 = 2, 1, 1)",
+    ];
+    check::<Value>(&inputs, Limits::default());
+    check::<i64>(&inputs, Limits::default());
+    check::<f64>(&inputs, Limits::default());
+    check::<BigInt>(&inputs, Limits::default());
+
+    // and both count the same steps and depths against the limits
+    let limits = Limits {
+      depth: 4,
+      steps: 6,
+      bits: 64,
+    };
+    let inputs = [
+      "1 + 2 + 3",
+      "1 + 2 + 3 + 4",
+      "x = 1 + 2",
+      "x = y = 1 + 2",
+      "---1",
+      "----1",
+      "max(1, -(2))",
+      "max(1, -(-2))",
+      "y = 2 * -(-(-(-3)))",
+      "y",
+      "2 ^ 65",
+      "solve(x = 2, x, 1)",
+      "-solve(x = 2, x, 1)",
+      "-solve(-(-x) = 2, x, 1)",
+    ];
+    check::<Value>(&inputs, limits);
+    check::<BigInt>(&inputs, limits);
+
+    let code = Code::<Value>::compile(&"x = 1 + y".parse().unwrap());
+    assert_eq!(
+      code.ops(),
+      &[
+        Op::Guard(0),
+        Op::Const(0),
+        Op::Load(1),
+        Op::BinOp(BinOpKind::Add),
+        Op::Store(0)
+      ]
+    );
+
+    // a host function defined after compiling still shadows the built-in one
+    let code = Code::<Value>::compile(&"sin(2)".parse().unwrap());
+    let mut interpreter = Interpreter::new();
+    interpreter
+      .context_mut()
+      .define_function("sin", 1, |args: &[Value]| Ok(args[0]));
+    assert_eq!(interpreter.run(&code), Ok(Value::Int(2)));
+
+    // and so does one named `solve`, whose arguments are then values like any others
+    let mut tree = Interpreter::new();
+    let mut vm = Interpreter::new();
+    for interpreter in [&mut tree, &mut vm] {
+      interpreter
+        .context_mut()
+        .define_function("solve", 3, |args: &[Value]| Ok(args[2]));
+    }
+    for input in [
+      "solve(1, 2, 3)",
+      "solve(x ^ 2 = 2, x, 1)",
+      "1 + solve(1, 2, max(3))",
+    ] {
+      let ast = input.parse().unwrap();
+      assert_eq!(vm.run(&Code::compile(&ast)), tree.eval(&ast), "{}", input);
+    }
+    assert_eq!(
+      vm.run(&Code::compile(&"solve(1, 2, 3)".parse().unwrap())),
+      Ok(Value::Int(3))
+    );
+  }
+}
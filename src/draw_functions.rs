@@ -80,8 +80,8 @@ pub mod renderers {
     cells: i64,
   }
 
-  impl Canvas {
-    pub fn default() -> Canvas {
+  impl Default for Canvas {
+    fn default() -> Canvas {
       Canvas {
         width: 600,
         height: 320,
@@ -89,7 +89,9 @@ pub mod renderers {
         cells: 100,
       }
     }
+  }
 
+  impl Canvas {
     pub fn new(width: i64, height: i64, xyrange: f64, cells: i64) -> Canvas {
       Canvas {
         width,
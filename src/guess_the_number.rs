@@ -1,32 +1,33 @@
 extern crate rand;
 
 pub mod dialogue {
+  use crate::i18n::{tr, tr_with};
   use rand::Rng;
   use std::cmp::Ordering;
   use std::io;
 
   pub fn main() {
-    let secret_number = rand::thread_rng().gen_range(1, 101);
+    let secret_number = rand::thread_rng().gen_range(1..101);
     loop {
-      println!("Input your number ...");
+      println!("{}", tr("guess.input"));
       let mut guess = String::new();
 
       io::stdin()
         .read_line(&mut guess)
-        .expect("Failed to read_line");
+        .unwrap_or_else(|_| panic!("{}", tr("guess.read_failed")));
 
       let guess: u32 = match guess.trim().parse() {
         Ok(num) => num,
         Err(_) => continue,
       };
 
-      println!("You guessed: {}", guess);
+      println!("{}", tr_with("guess.you_guessed", &[&guess]));
 
       match guess.cmp(&secret_number) {
-        Ordering::Less => println!("Too small"),
-        Ordering::Greater => println!("Too large"),
+        Ordering::Less => println!("{}", tr("guess.too_small")),
+        Ordering::Greater => println!("{}", tr("guess.too_large")),
         Ordering::Equal => {
-          println!("You win!");
+          println!("{}", tr("guess.win"));
           break;
         }
       }
@@ -0,0 +1,1057 @@
+// the message catalog: every text the user reads, in English and in Japanese.
+// a key missing in a language falls back to English
+
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Locale {
+  En,
+  Ja,
+}
+
+impl Locale {
+  // the language of the environment as `setlocale` picks it, English when unknown
+  pub fn from_env() -> Locale {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+      .iter()
+      .filter_map(|var| std::env::var(var).ok())
+      .find(|value| !value.is_empty())
+      .and_then(|value| value.parse().ok())
+      .unwrap_or(Locale::En)
+  }
+
+  fn catalog(self) -> &'static [(&'static str, &'static str)] {
+    match self {
+      Locale::En => &EN,
+      Locale::Ja => &JA,
+    }
+  }
+}
+
+// accepts both `ja` and environment values such as `ja_JP.UTF-8`
+impl FromStr for Locale {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.split(['_', '-', '.']).next().unwrap() {
+      "en" | "C" | "POSIX" => Ok(Locale::En),
+      "ja" => Ok(Locale::Ja),
+      _ => Err(tr_with("repl.unknown_locale", &[&s])),
+    }
+  }
+}
+
+// per thread, so tests running side by side do not see each other's language
+thread_local! {
+  static LOCALE: Cell<Locale> = const { Cell::new(Locale::En) };
+}
+
+pub fn locale() -> Locale {
+  LOCALE.with(|l| l.get())
+}
+
+pub fn set_locale(locale: Locale) {
+  LOCALE.with(|l| l.set(locale))
+}
+
+fn lookup(locale: Locale, key: &str) -> Option<&'static str> {
+  locale
+    .catalog()
+    .iter()
+    .find(|(k, _)| *k == key)
+    .map(|&(_, message)| message)
+}
+
+// the message for `key` in the current language. an unknown key is shown as it is,
+// which makes a typo visible rather than hiding the message
+pub fn tr(key: &'static str) -> &'static str {
+  lookup(locale(), key)
+    .or_else(|| lookup(Locale::En, key))
+    .unwrap_or(key)
+}
+
+// `tr` with `{0}`, `{1}` and so on replaced by `args`, which a language may reorder
+pub fn tr_with(key: &'static str, args: &[&dyn fmt::Display]) -> String {
+  let mut out = String::new();
+  let mut rest = tr(key);
+  while let Some(open) = rest.find('{') {
+    out += &rest[..open];
+    let close = open + rest[open..].find('}').unwrap();
+    let n: usize = rest[open + 1..close].parse().unwrap();
+    out += &args[n].to_string();
+    rest = &rest[close + 1..];
+  }
+  out + rest
+}
+
+const EN: [(&str, &str); 130] = [
+  ("error.lexer", "lexer error [{0}]"),
+  ("error.parser", "parser error [{0}]"),
+  ("error.interpreter", "interpreter error [{0}]"),
+  ("error.multiple", "{0} errors"),
+  ("trace.caused_by", "caused by {0}"),
+  ("lex.invalid_char", "invalid char '{0}'"),
+  ("lex.number_too_large", "number is too large"),
+  ("eof", "End of file"),
+  ("parse.unexpected_token", "'{0}' is not expected"),
+  ("parse.not_expression", "'{0}' is not a start of expression"),
+  ("parse.not_operator", "'{0}' is not an operator"),
+  ("parse.unclosed_paren", "'{0}' is not closed"),
+  ("parse.redundant", "expression after '{0}' is redundant"),
+  (
+    "parse.not_assignable",
+    "left hand side of '{0}' is not a variable",
+  ),
+  ("parse.too_deep", "'{0}' nests deeper than {1} levels"),
+  ("interp.division_by_zero", "zero division error"),
+  ("interp.overflow", "integer overflow"),
+  ("interp.unsupported", "{0} is not supported in {1} mode"),
+  ("interp.domain", "domain error: {0}"),
+  ("interp.undefined_variable", "undefined variable '{0}'"),
+  ("interp.unknown_function", "unknown function '{0}'"),
+  ("interp.arity", "'{0}' takes {1} {2} but {3} {4} given"),
+  ("interp.syntax_error", "the expression could not be parsed"),
+  (
+    "interp.read_only",
+    "'{0}' is defined by the host and cannot be assigned to",
+  ),
+  (
+    "interp.not_differentiable",
+    "'{0}' cannot be differentiated",
+  ),
+  (
+    "interp.no_sign_change",
+    "the expression has the same sign at {0} and {1}",
+  ),
+  ("interp.no_convergence", "no root found from {0}"),
+  (
+    "interp.limit_depth",
+    "the expression nests deeper than {0} levels",
+  ),
+  (
+    "interp.limit_steps",
+    "the evaluation takes more than {0} steps",
+  ),
+  ("interp.limit_bits", "a value is larger than {0} bits"),
+  (
+    "interp.not_variable",
+    "'{0}' is not a variable to solve for",
+  ),
+  // English needs these for its plurals, Japanese has none
+  ("word.argument", "argument"),
+  ("word.arguments", "arguments"),
+  ("word.was", "was"),
+  ("word.were", "were"),
+  ("domain.undefined_for", "{0} is undefined for {1}: {2}"),
+  (
+    "domain.between_one",
+    "the argument must be between -1 and 1",
+  ),
+  ("domain.positive", "the argument must be positive"),
+  ("domain.not_negative", "the argument must not be negative"),
+  (
+    "domain.natural",
+    "the argument must be a whole number, 0 or more",
+  ),
+  ("domain.log", "log is undefined for {0} in base {1}"),
+  (
+    "domain.negative_to_fraction",
+    "a negative number {0} to the power of a fraction {1} is not real",
+  ),
+  ("domain.not_integer", "{0} is not an integer"),
+  (
+    "domain.zero_to_complex",
+    "zero to a complex power is undefined",
+  ),
+  ("domain.nan", "NaN cannot be compared"),
+  ("label.not_token", "not part of any token"),
+  ("label.float_range", "this is out of the range of floats"),
+  ("note.float_max", "the largest float is about 1.8e308"),
+  ("label.input_ends", "the input ends here"),
+  ("label.expected_comma", "expected ',' or ')'"),
+  ("label.expected_start", "expected a number, a name or '('"),
+  ("label.expected_operator", "expected an operator"),
+  ("label.unclosed_paren", "this parenthesis is never closed"),
+  ("help.close_paren", "add a ')' after the expression"),
+  ("label.expression_ended", "the expression has already ended"),
+  ("help.join", "join the expressions with an operator"),
+  (
+    "label.not_assignable",
+    "cannot assign to the left hand side",
+  ),
+  (
+    "help.assign_variable",
+    "only a variable can be assigned to, as in 'x = 1'",
+  ),
+  ("label.expected_expression", "expected an expression"),
+  ("label.division_by_zero", "division by zero"),
+  ("label.evaluates_to_zero", "this evaluates to zero"),
+  ("label.negative_power_of_zero", "a negative power of zero"),
+  ("label.too_large", "the result is too large"),
+  (
+    "help.bignum",
+    "':mode bignum' computes with integers of any size",
+  ),
+  ("label.unsupported", "not supported here"),
+  (
+    "help.switch_mode",
+    "switch to another mode with ':mode <name>'",
+  ),
+  ("label.domain", "outside the domain"),
+  ("label.not_defined", "not defined"),
+  ("help.assign_first", "assign it first, as in '{0} = 1'"),
+  ("label.not_builtin", "not a built-in function"),
+  ("note.functions", "the built-in functions are {0}"),
+  ("label.called_with", "called with {0} {1}"),
+  ("label.not_parsed", "this could not be parsed"),
+  ("label.read_only", "cannot assign to this name"),
+  ("help.another_name", "store the value under another name"),
+  ("label.not_differentiable", "no derivative for this"),
+  ("label.same_sign", "no sign change between the ends"),
+  (
+    "help.opposite_signs",
+    "give ends where the values have opposite signs",
+  ),
+  ("label.no_root", "no root found for this"),
+  ("label.not_variable", "expected a variable"),
+  (
+    "help.another_start",
+    "start from another guess, or give an interval",
+  ),
+  ("label.too_deep", "nested too deeply"),
+  ("label.over_limit", "over the limit here"),
+  (
+    "help.split_expression",
+    "assign parts of the expression to variables first",
+  ),
+  ("repl.prompt", "> "),
+  ("repl.unknown_command", "unknown command ':{0}'"),
+  (
+    "repl.unknown_mode",
+    "unknown mode '{0}', expected one of machine, int, float, bignum, rational or complex",
+  ),
+  ("repl.not_a_code", "'{0}' is not an error code"),
+  (
+    "repl.unknown_locale",
+    "unknown language '{0}', expected en or ja",
+  ),
+  (
+    "repl.explain_usage",
+    "--explain takes an error code such as C0201",
+  ),
+  (
+    "repl.diff_usage",
+    ":diff takes an expression and a variable, as in ':diff x ^ 2, x'",
+  ),
+  ("repl.trace_usage", ":trace takes on or off"),
+  ("repl.values_usage", ":values takes on or off"),
+  (
+    "repl.solve_usage",
+    ":solve takes an equation, a variable and a guess or two ends, as in ':solve x ^ 2 = 2, x, 1'",
+  ),
+  (
+    "repl.limit_usage",
+    "{0} takes a whole number, as in '{0} 1000'",
+  ),
+  (
+    "repl.lang_usage",
+    "{0} takes a language, en or ja, as in '{0} ja'",
+  ),
+  ("limit.depth", "depth {0}"),
+  ("limit.steps", "{0} steps"),
+  ("limit.bits", "{0} bits"),
+  (
+    "explain.C0001",
+    "\
+A character that cannot start any token.
+
+Expressions are made of numbers, names, the operators `+ - * / ^ ** !`, the
+parentheses, `=` and `,`. Anything else, such as `$` or `;`, is rejected.
+
+Erroneous example:
+
+    1 + 2 $ 3
+
+Remove the character, or replace it with the operator that was meant.",
+  ),
+  (
+    "explain.C0002",
+    "\
+A number literal too large to be represented.
+
+Float literals, those with a `.` or an exponent, must stay below about
+1.8e308. Integer literals are not limited, but a mode may still reject them
+when it evaluates them (see C0202).
+
+Erroneous example:
+
+    1e400
+
+Use a smaller exponent, or an integer literal in the bignum mode.",
+  ),
+  (
+    "explain.C0003",
+    "\
+The input ended in the middle of a token.
+
+The lexer reports this when a token needs more characters than the input has
+left. Complete the token.",
+  ),
+  (
+    "explain.C0101",
+    "\
+A token that cannot appear at this place in a function call.
+
+The arguments of a call are expressions separated by `,` and closed by `)`.
+
+Erroneous example:
+
+    max(1 2)
+
+Separate the arguments with a comma: `max(1, 2)`.",
+  ),
+  (
+    "explain.C0102",
+    "\
+An expression was expected, but the token cannot start one.
+
+An expression starts with a number, a name, `(` or a prefix operator such as
+`-`.
+
+Erroneous example:
+
+    1 + * 2
+
+Remove the extra operator, or add the missing operand: `1 + 3 * 2`.",
+  ),
+  (
+    "explain.C0103",
+    "\
+An operator was expected, but the token is not one.
+
+Operands have to be joined with an operator, as in `1 + 2`. The parser
+reports two operands next to each other as C0105 instead, so this code is
+kept only so that it is never given to another error.",
+  ),
+  (
+    "explain.C0104",
+    "\
+A `(` without a matching `)`.
+
+Erroneous example:
+
+    2 * (3 + 4
+
+Add the `)` where the parenthesized expression ends: `2 * (3 + 4)`.",
+  ),
+  (
+    "explain.C0105",
+    "\
+The expression is complete, but more input follows it.
+
+Two expressions next to each other are not multiplied, and a stray `)` has
+no `(` to close.
+
+Erroneous example:
+
+    2 (3 + 4)
+
+Write the operator between them: `2 * (3 + 4)`.",
+  ),
+  (
+    "explain.C0106",
+    "\
+The left hand side of `=` is not a variable.
+
+Only a plain name can be assigned to.
+
+Erroneous example:
+
+    x + 1 = 2
+
+Assign to a name: `x = 2 - 1`.",
+  ),
+  (
+    "explain.C0107",
+    "\
+The input ended where an expression was expected.
+
+Erroneous example:
+
+    1 +
+
+Complete the expression: `1 + 2`.",
+  ),
+  (
+    "explain.C0108",
+    "\
+An expression nested more deeply than the parser allows.
+
+Parentheses, prefix operators, the right operands of infix operators and the
+arguments of calls each nest the expression inside them one level deeper,
+while a chain such as `1 + 2 + 3` is as flat as it reads. Past the limit, 256
+levels unless the program running the calculator sets another, the rest of the
+input is not parsed.
+
+Erroneous example:
+
+    ((((((((((((((((((((((((((((((((((((( ... 1 ... )))))))))))))))))))))))))))))))))))))
+
+Assign parts of the expression to variables and use them instead.",
+  ),
+  (
+    "explain.C0201",
+    "\
+A division by zero.
+
+The right hand side of `/` evaluated to zero, or zero was raised to a
+negative power. No mode has a value for this, not even the float mode.
+
+Erroneous example:
+
+    1 / (2 - 2)
+
+Make sure the divisor cannot be zero.",
+  ),
+  (
+    "explain.C0202",
+    "\
+A result too large for the number type of the mode.
+
+The machine and int modes compute with 64-bit integers, which go up to
+9223372036854775807, and a float overflows past about 1.8e308.
+
+Erroneous example:
+
+    2^64
+
+Switch to the bignum or rational mode, whose integers have no limit:
+`:mode bignum`.",
+  ),
+  (
+    "explain.C0203",
+    "\
+An operation the number type of the mode does not have.
+
+For example, complex numbers have no order, so the complex mode cannot round
+them down.
+
+Erroneous example, in the complex mode:
+
+    floor(i)
+
+Switch to a mode with the operation, such as `:mode float`.",
+  ),
+  (
+    "explain.C0204",
+    "\
+An operand outside the domain of the operation.
+
+The function is not defined there, at least not in real numbers.
+
+Erroneous example:
+
+    sqrt(-1)
+
+Check the operand, or switch to the complex mode: `:mode complex`.",
+  ),
+  (
+    "explain.C0205",
+    "\
+A variable read before any value is assigned to it.
+
+Erroneous example:
+
+    x * 2
+
+Assign it first: `x = 21`, then `x * 2`. The result of the last input is
+always available as `ans`.",
+  ),
+  (
+    "explain.C0206",
+    "\
+A call of a function that does not exist.
+
+Erroneous example:
+
+    sine(1)
+
+Check the spelling. The error lists the functions there are.",
+  ),
+  (
+    "explain.C0207",
+    "\
+A function called with the wrong number of arguments.
+
+Erroneous example:
+
+    max(1)
+
+Pass as many arguments as the function takes: `max(1, 2)`.",
+  ),
+  (
+    "explain.C0208",
+    "\
+An expression with a syntax error was evaluated.
+
+The parser can give a tree with the places it could not parse marked, and
+evaluating one of those places fails.
+
+Fix the syntax errors reported for the input first.",
+  ),
+  (
+    "explain.C0209",
+    "\
+An assignment to a name the host program defines.
+
+A program embedding the calculator can define constants, variables and
+functions of its own. They are read-only, so that an input cannot change what
+the program relies on.
+
+Erroneous example, where the program defines `cpu_count`:
+
+    cpu_count = 4
+
+Store the value under another name: `cpus = 4`.",
+  ),
+  (
+    "explain.C0210",
+    "\
+An expression `diff` has no derivative for.
+
+Sums, products, quotients, powers and the built-in functions are
+differentiated, except `floor`, `ceil`, `min` and `max`, which have corners or
+jumps, and `fact`, which is defined on whole numbers only. An assignment, a function the host program defines and an unknown
+function cannot be differentiated either.
+
+Erroneous example:
+
+    :diff floor(x), x
+
+Write the expression without the function, or differentiate each piece of it.",
+  ),
+  (
+    "explain.C0211",
+    "\
+An interval given to `solve` with no sign change between its ends.
+
+Given two ends, `solve` narrows the interval down to a root, which it can only
+do when the expression is negative at one end and positive at the other.
+
+Erroneous example:
+
+    solve(x ^ 2 = 2, x, 2, 3)
+
+Give ends on either side of a root, `solve(x ^ 2 = 2, x, 0, 3)`, or a single
+guess near it.",
+  ),
+  (
+    "explain.C0212",
+    "\
+A search for a root which did not find one.
+
+From a guess, `solve` follows the tangent of the expression with Newton's
+method, and where that goes astray looks for a sign change around the guess to
+narrow down instead. Without either, the expression may have no real root, or
+none near the guess.
+
+Erroneous example:
+
+    solve(x ^ 2 + 1 = 0, x, 1)
+
+Check that the equation has a root, and start from a guess nearer to it.",
+  ),
+  (
+    "explain.C0213",
+    "\
+An evaluation which went past a limit of the interpreter.
+
+So that no input can use up the time or the memory of the program running the
+calculator, each evaluation is limited in how deeply its expression nests, in
+how many sub-expressions it reduces, and in how large its numbers get in the
+bignum and rational modes. The defaults are 256 levels, a million steps and
+65536 bits, about 20,000 decimal digits.
+
+Erroneous example:
+
+    2 ^ 100000000
+
+Split the computation into smaller ones, or work with floats, which stay the
+same size.",
+  ),
+  (
+    "explain.C0214",
+    "\
+Something other than a variable given to `solve` to solve for.
+
+The second argument of `solve` names the variable the search tries values of,
+so it must be a name, not an expression or a number.
+
+Erroneous example:
+
+    solve(x ^ 2 = 2, 2 * x, 1)
+
+Solve for the variable itself, `solve(x ^ 2 = 2, x, 1)`.",
+  ),
+  ("guess.input", "Input your number ..."),
+  ("guess.read_failed", "Failed to read_line"),
+  ("guess.you_guessed", "You guessed: {0}"),
+  ("guess.too_small", "Too small"),
+  ("guess.too_large", "Too large"),
+  ("guess.win", "You win!"),
+];
+
+const JA: [(&str, &str); 125] = [
+  ("error.lexer", "字句解析エラー [{0}]"),
+  ("error.parser", "構文解析エラー [{0}]"),
+  ("error.interpreter", "評価エラー [{0}]"),
+  ("error.multiple", "{0} 個のエラー"),
+  ("trace.caused_by", "原因: {0}"),
+  ("lex.invalid_char", "不正な文字 '{0}'"),
+  ("lex.number_too_large", "数が大きすぎます"),
+  ("eof", "入力が終わりました"),
+  ("parse.unexpected_token", "'{0}' はここに置けません"),
+  ("parse.not_expression", "'{0}' は式の始まりになれません"),
+  ("parse.not_operator", "'{0}' は演算子ではありません"),
+  ("parse.unclosed_paren", "'{0}' が閉じられていません"),
+  ("parse.redundant", "'{0}' の後の式は余分です"),
+  ("parse.not_assignable", "'{0}' の左辺が変数ではありません"),
+  ("parse.too_deep", "'{0}' の入れ子が {1} 段を超えています"),
+  ("interp.division_by_zero", "ゼロ除算エラー"),
+  ("interp.overflow", "整数のオーバーフロー"),
+  ("interp.unsupported", "{1} モードでは {0} を使えません"),
+  ("interp.domain", "定義域エラー: {0}"),
+  ("interp.undefined_variable", "未定義の変数 '{0}'"),
+  ("interp.unknown_function", "未知の関数 '{0}'"),
+  ("interp.arity", "'{0}' の引数は {1} 個ですが {3} 個渡されました"),
+  ("interp.syntax_error", "式を解析できませんでした"),
+  ("interp.read_only", "'{0}' はホストが定義した名前なので代入できません"),
+  ("interp.not_differentiable", "'{0}' は微分できません"),
+  ("interp.no_sign_change", "式の値が {0} と {1} で同じ符号です"),
+  ("interp.no_convergence", "{0} から根が見つかりませんでした"),
+  ("interp.limit_depth", "式の入れ子が {0} 段を超えています"),
+  ("interp.limit_steps", "評価が {0} ステップを超えています"),
+  ("interp.limit_bits", "値が {0} ビットを超えています"),
+  ("interp.not_variable", "'{0}' は解く対象の変数ではありません"),
+  ("domain.undefined_for", "{0} は {1} で定義されていません: {2}"),
+  ("domain.between_one", "引数は -1 以上 1 以下でなければなりません"),
+  ("domain.positive", "引数は正でなければなりません"),
+  ("domain.not_negative", "引数は負であってはなりません"),
+  ("domain.natural", "引数は 0 以上の整数でなければなりません"),
+  ("domain.log", "底が {1} の log は {0} で定義されていません"),
+  ("domain.negative_to_fraction", "負の数 {0} の分数 {1} 乗は実数ではありません"),
+  ("domain.not_integer", "{0} は整数ではありません"),
+  ("domain.zero_to_complex", "0 の複素数乗は定義されていません"),
+  ("domain.nan", "NaN は比較できません"),
+  ("label.not_token", "どのトークンにもなりません"),
+  ("label.float_range", "浮動小数点数の範囲を超えています"),
+  ("note.float_max", "浮動小数点数の最大値はおよそ 1.8e308 です"),
+  ("label.input_ends", "入力はここで終わっています"),
+  ("label.expected_comma", "',' か ')' が必要です"),
+  ("label.expected_start", "数、名前、'(' のいずれかが必要です"),
+  ("label.expected_operator", "演算子が必要です"),
+  ("label.unclosed_paren", "この括弧は閉じられていません"),
+  ("help.close_paren", "式の後に ')' を追加してください"),
+  ("label.expression_ended", "式はここより前で終わっています"),
+  ("help.join", "式と式を演算子でつないでください"),
+  ("label.not_assignable", "左辺には代入できません"),
+  ("help.assign_variable", "代入できるのは 'x = 1' のように変数だけです"),
+  ("label.expected_expression", "式が必要です"),
+  ("label.division_by_zero", "ゼロで割っています"),
+  ("label.evaluates_to_zero", "これはゼロになります"),
+  ("label.negative_power_of_zero", "ゼロの負のべき乗です"),
+  ("label.too_large", "結果が大きすぎます"),
+  ("help.bignum", "':mode bignum' なら任意の大きさの整数で計算できます"),
+  ("label.unsupported", "ここでは使えません"),
+  ("help.switch_mode", "':mode <名前>' で別のモードに切り替えてください"),
+  ("label.domain", "定義域の外です"),
+  ("label.not_defined", "定義されていません"),
+  ("help.assign_first", "先に '{0} = 1' のように代入してください"),
+  ("label.not_builtin", "組み込み関数ではありません"),
+  ("note.functions", "組み込み関数は {0} です"),
+  ("label.called_with", "{0} 個の引数で呼ばれています"),
+  ("label.not_parsed", "ここを解析できませんでした"),
+  ("label.read_only", "この名前には代入できません"),
+  ("help.another_name", "別の名前に代入してください"),
+  ("label.not_differentiable", "ここは微分できません"),
+  ("label.same_sign", "両端の間で符号が変わりません"),
+  ("help.opposite_signs", "値の符号が異なる両端を指定してください"),
+  ("label.no_root", "根が見つかりません"),
+  ("label.not_variable", "変数が必要です"),
+  ("help.another_start", "別の初期値か区間を指定してください"),
+  ("label.too_deep", "入れ子が深すぎます"),
+  ("label.over_limit", "ここで上限を超えました"),
+  ("help.split_expression", "式の一部を先に変数に代入してください"),
+  ("repl.unknown_command", "不明なコマンド ':{0}'"),
+  ("repl.unknown_mode", "不明なモード '{0}'。machine、int、float、bignum、rational、complex のいずれかを指定してください"),
+  ("repl.unknown_locale", "不明な言語 '{0}'。en か ja を指定してください"),
+  ("repl.explain_usage", "--explain には C0201 のようなエラーコードを指定してください"),
+  ("repl.diff_usage", ":diff には ':diff x ^ 2, x' のように式と変数を指定してください"),
+  ("repl.trace_usage", ":trace には on か off を指定してください"),
+  ("repl.values_usage", ":values には on か off を指定してください"),
+  ("repl.solve_usage", ":solve には ':solve x ^ 2 = 2, x, 1' のように方程式、変数、初期値か区間の両端を指定してください"),
+  ("repl.limit_usage", "{0} には '{0} 1000' のように整数を指定してください"),
+  ("repl.lang_usage", "{0} には '{0} ja' のように言語 en か ja を指定してください"),
+  ("repl.not_a_code", "'{0}' はエラーコードではありません"),
+  ("limit.depth", "深さ {0}"),
+  ("limit.steps", "{0} ステップ"),
+  ("limit.bits", "{0} ビット"),
+  (
+    "explain.C0001",
+    "\
+どのトークンの始まりにもならない文字です。
+
+式は数、名前、演算子 `+ - * / ^ ** !`、括弧、`=` と `,` からなります。`$` や `;`
+のようなそれ以外の文字は受け付けません。
+
+誤りの例:
+
+    1 + 2 $ 3
+
+その文字を取り除くか、意図した演算子に置き換えてください。",
+  ),
+  (
+    "explain.C0002",
+    "\
+大きすぎて表せない数のリテラルです。
+
+浮動小数点数のリテラル、つまり `.` や指数を持つものは、約 1.8e308 より小さく
+なければなりません。整数のリテラルに上限はありませんが、評価するときにモードが
+受け付けないことはあります (C0202 を参照)。
+
+誤りの例:
+
+    1e400
+
+指数を小さくするか、bignum モードで整数のリテラルを使ってください。",
+  ),
+  (
+    "explain.C0003",
+    "\
+トークンの途中で入力が終わりました。
+
+トークンに入力の残りより多くの文字が必要なとき、字句解析器がこれを報告します。
+トークンを完成させてください。",
+  ),
+  (
+    "explain.C0101",
+    "\
+関数呼び出しのこの位置には置けないトークンです。
+
+呼び出しの引数は `,` で区切られ `)` で閉じられる式です。
+
+誤りの例:
+
+    max(1 2)
+
+引数をカンマで区切ってください: `max(1, 2)`。",
+  ),
+  (
+    "explain.C0102",
+    "\
+式が来るべきところに、式を始められないトークンがあります。
+
+式は数、名前、`(`、または `-` のような前置演算子で始まります。
+
+誤りの例:
+
+    1 + * 2
+
+余分な演算子を取り除くか、欠けている被演算子を補ってください: `1 + 3 * 2`。",
+  ),
+  (
+    "explain.C0103",
+    "\
+演算子が来るべきところに、演算子でないトークンがあります。
+
+被演算子は `1 + 2` のように演算子でつなぐ必要があります。並んだ二つの被演算子は
+構文解析器が代わりに C0105 として報告するので、このコードは他のエラーに
+割り当てられないように残されているだけです。",
+  ),
+  (
+    "explain.C0104",
+    "\
+対応する `)` のない `(` です。
+
+誤りの例:
+
+    2 * (3 + 4
+
+括弧の中の式が終わるところに `)` を加えてください: `2 * (3 + 4)`。",
+  ),
+  (
+    "explain.C0105",
+    "\
+式は完結していますが、その後にまだ入力が続いています。
+
+並んだ二つの式は掛け合わされず、余分な `)` には閉じる `(` がありません。
+
+誤りの例:
+
+    2 (3 + 4)
+
+間に演算子を書いてください: `2 * (3 + 4)`。",
+  ),
+  (
+    "explain.C0106",
+    "\
+`=` の左辺が変数ではありません。
+
+代入できるのはただの名前だけです。
+
+誤りの例:
+
+    x + 1 = 2
+
+名前に代入してください: `x = 2 - 1`。",
+  ),
+  (
+    "explain.C0107",
+    "\
+式が来るべきところで入力が終わりました。
+
+誤りの例:
+
+    1 +
+
+式を完成させてください: `1 + 2`。",
+  ),
+  (
+    "explain.C0108",
+    "\
+構文解析器が許すより深く入れ子になった式です。
+
+括弧、前置演算子、中置演算子の右の被演算子、呼び出しの引数は、それぞれ中の式を
+一段深く入れ子にしますが、`1 + 2 + 3` のような連なりは見たとおり平らです。
+上限、つまり電卓を動かすプログラムが別に設定しない限り 256 段を超えると、
+入力の残りは解析されません。
+
+誤りの例:
+
+    ((((((((((((((((((((((((((((((((((((( ... 1 ... )))))))))))))))))))))))))))))))))))))
+
+式の一部を変数に代入し、代わりにそれを使ってください。",
+  ),
+  (
+    "explain.C0201",
+    "\
+ゼロによる除算です。
+
+`/` の右辺がゼロと評価されたか、ゼロが負の数で累乗されました。どのモードにも、
+float モードにさえ、これに当たる値はありません。
+
+誤りの例:
+
+    1 / (2 - 2)
+
+除数がゼロになりえないことを確かめてください。",
+  ),
+  (
+    "explain.C0202",
+    "\
+モードの数の型には大きすぎる結果です。
+
+machine と int のモードは 9223372036854775807 までの 64 ビット整数で計算し、
+浮動小数点数は約 1.8e308 を超えるとあふれます。
+
+誤りの例:
+
+    2^64
+
+整数に上限のない bignum か rational のモードに切り替えてください:
+`:mode bignum`。",
+  ),
+  (
+    "explain.C0203",
+    "\
+モードの数の型にない演算です。
+
+たとえば複素数には大小がないので、complex モードでは切り捨てられません。
+
+complex モードでの誤りの例:
+
+    floor(i)
+
+`:mode float` のような、その演算のあるモードに切り替えてください。",
+  ),
+  (
+    "explain.C0204",
+    "\
+演算の定義域の外にある被演算子です。
+
+関数はそこで、少なくとも実数の範囲では定義されていません。
+
+誤りの例:
+
+    sqrt(-1)
+
+被演算子を確かめるか、complex モードに切り替えてください: `:mode complex`。",
+  ),
+  (
+    "explain.C0205",
+    "\
+値が代入される前に読まれた変数です。
+
+誤りの例:
+
+    x * 2
+
+先に代入してください: `x = 21` の後に `x * 2`。直前の入力の結果はいつでも
+`ans` で使えます。",
+  ),
+  (
+    "explain.C0206",
+    "\
+存在しない関数の呼び出しです。
+
+誤りの例:
+
+    sine(1)
+
+綴りを確かめてください。エラーには使える関数の一覧が示されます。",
+  ),
+  (
+    "explain.C0207",
+    "\
+間違った数の引数で呼ばれた関数です。
+
+誤りの例:
+
+    max(1)
+
+関数が取る数だけ引数を渡してください: `max(1, 2)`。",
+  ),
+  (
+    "explain.C0208",
+    "\
+構文エラーのある式が評価されました。
+
+構文解析器は解析できなかった箇所に印を付けた木を返すことがあり、その箇所を
+評価すると失敗します。
+
+先に入力について報告された構文エラーを直してください。",
+  ),
+  (
+    "explain.C0209",
+    "\
+ホストのプログラムが定義する名前への代入です。
+
+電卓を組み込むプログラムは独自の定数、変数、関数を定義できます。それらは読み出し
+専用なので、入力によってプログラムが頼りにするものが変わることはありません。
+
+プログラムが `cpu_count` を定義しているときの誤りの例:
+
+    cpu_count = 4
+
+値は別の名前で保存してください: `cpus = 4`。",
+  ),
+  (
+    "explain.C0210",
+    "\
+`diff` が導関数を求められない式です。
+
+和、積、商、累乗、組み込み関数は微分されますが、角や飛びのある `floor`、`ceil`、
+`min`、`max` と、整数でしか定義されない `fact` は除きます。代入、ホストの
+プログラムが定義する関数、未知の関数も微分できません。
+
+誤りの例:
+
+    :diff floor(x), x
+
+その関数を使わずに式を書くか、式の部分ごとに微分してください。",
+  ),
+  (
+    "explain.C0211",
+    "\
+`solve` に与えられた、両端の間で符号の変わらない区間です。
+
+両端が与えられると `solve` は区間を根まで狭めますが、それができるのは一方の端で
+式が負、もう一方の端で正のときだけです。
+
+誤りの例:
+
+    solve(x ^ 2 = 2, x, 2, 3)
+
+根を挟む両端を与えるか、`solve(x ^ 2 = 2, x, 0, 3)`、根に近い推測値を一つ
+与えてください。",
+  ),
+  (
+    "explain.C0212",
+    "\
+根が見つからなかった探索です。
+
+`solve` は推測値からニュートン法で式の接線をたどり、それがうまくいかないところでは
+推測値の周りで符号の変わるところを探して狭めていきます。どちらもできないときは、
+式に実数の根がないか、推測値の近くにないのかもしれません。
+
+誤りの例:
+
+    solve(x ^ 2 + 1 = 0, x, 1)
+
+方程式に根があることを確かめ、根により近い推測値から始めてください。",
+  ),
+  (
+    "explain.C0213",
+    "\
+インタプリタの上限を超えた評価です。
+
+どんな入力も電卓を動かすプログラムの時間やメモリを使い果たせないように、評価は
+それぞれ、式の入れ子の深さ、簡約する部分式の数、bignum と rational のモードでの
+数の大きさを制限されています。既定値は 256 段、100 万ステップ、65536 ビット
+(10 進数で約 20,000 桁) です。
+
+誤りの例:
+
+    2 ^ 100000000
+
+計算をより小さなものに分けるか、大きさの変わらない浮動小数点数で計算してください。",
+  ),
+  (
+    "explain.C0214",
+    "\
+`solve` に解く対象として与えられた、変数でないものです。
+
+`solve` の 2 番目の引数は探索が値を試す変数を指すので、式や数ではなく名前で
+なければなりません。
+
+誤りの例:
+
+    solve(x ^ 2 = 2, 2 * x, 1)
+
+変数そのものについて解いてください: `solve(x ^ 2 = 2, x, 1)`。",
+  ),
+  ("guess.input", "数を入力してください ..."),
+  ("guess.read_failed", "入力を読み込めませんでした"),
+  ("guess.you_guessed", "あなたの予想: {0}"),
+  ("guess.too_small", "小さすぎます"),
+  ("guess.too_large", "大きすぎます"),
+  ("guess.win", "正解です!"),
+];
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_catalog() {
+    // every translated key is an English one, so nothing is only in Japanese
+    assert!(JA.iter().all(|(key, _)| lookup(Locale::En, key).is_some()));
+    assert_eq!("ja_JP.UTF-8".parse(), Ok(Locale::Ja));
+    assert_eq!("en".parse(), Ok(Locale::En));
+
+    assert_eq!(
+      tr_with("interp.unsupported", &[&"pow", &"int"]),
+      "pow is not supported in int mode"
+    );
+    set_locale(Locale::Ja);
+    assert_eq!(
+      tr_with("interp.unsupported", &[&"pow", &"int"]),
+      "int モードでは pow を使えません"
+    );
+    // untranslated keys are shown in English
+    assert_eq!(tr("repl.prompt"), "> ");
+    assert_eq!(tr("no.such.key"), "no.such.key");
+    set_locale(Locale::En);
+  }
+}
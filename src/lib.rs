@@ -0,0 +1,13 @@
+// the calculator as a library, to embed it without the REPL
+
+pub mod calculator_ref;
+pub mod i18n;
+
+pub use calculator_ref::ast::{parse, Ast, AstKind, BinOp, BinOpKind, UniOp, UniOpKind};
+pub use calculator_ref::context::Context;
+pub use calculator_ref::interpreter::{
+  Evaluator, Interpreter, InterpreterError, InterpreterErrorKind, Mode, Value,
+};
+pub use calculator_ref::lexer::{lex, Token, TokenKind};
+pub use calculator_ref::vm::Code;
+pub use calculator_ref::{evaluate, Annot, Error, Loc};
@@ -1,5 +1,8 @@
 mod calculator;
-mod calculator_ref;
+#[allow(dead_code)]
+mod guess_the_number;
+use rust_tutorial::calculator_ref;
+use rust_tutorial::i18n::{self, tr, tr_with};
 
 mod draw_functions;
 pub use crate::draw_functions::functions as funcs;
@@ -26,10 +29,237 @@ fn prompt(s: &str) -> io::Result<()> {
   stdout.flush()
 }
 
+// what the REPL shows of an evaluation besides its result, kept across `:mode` switches
+#[derive(Debug, Clone, Copy, Default)]
+struct Show {
+  // each step of the evaluation, as `1 + 2 * 3 → 1 + 6 → 7`
+  steps: bool,
+  // the value of every sub-expression under the input
+  values: bool,
+  // with `--json`, everything the REPL prints, the output of commands included, is one
+  // JSON object per line, and the steps and the values are not shown. the spans of errors
+  // in the expression of a command point into that expression, as its diagnostics do, and
+  // a command which succeeds without output, such as `:mode float`, prints nothing
+  json: bool,
+}
+
+impl Show {
+  // a result of an input or a command
+  fn result(self, text: impl std::fmt::Display) {
+    if self.json {
+      println!("{}", calculator_ref::json::result(text.to_string()));
+    } else {
+      println!("{}", text);
+    }
+  }
+
+  // what is wrong with a command, rather than with an expression in it
+  fn command_error(self, message: impl std::fmt::Display) {
+    if self.json {
+      println!(
+        "{}",
+        calculator_ref::json::command_error(message.to_string())
+      );
+    } else {
+      eprintln!("{}", message);
+    }
+  }
+
+  // the errors of an input which does not parse
+  fn parse_error(self, e: calculator_ref::Error, input: &str) {
+    if self.json {
+      e.to_json(input).iter().for_each(|e| println!("{}", e));
+    } else {
+      e.show_diagnostic(input);
+      calculator_ref::show_trace(e);
+    }
+  }
+
+  // the error evaluating a parsed input
+  fn eval_error(
+    self,
+    e: calculator_ref::interpreter::InterpreterError,
+    input: &str,
+    ast: &calculator_ref::ast::Ast,
+  ) {
+    if self.json {
+      println!("{}", e.to_json(input));
+    } else {
+      e.show_diagnostic(input, ast);
+      calculator_ref::show_trace(e);
+    }
+  }
+}
+
+// REPL commands start with ':' so they never clash with expressions
+fn run_command(
+  interpreter: &mut Box<dyn calculator_ref::interpreter::Evaluator>,
+  show: &mut Show,
+  command: &str,
+) {
+  use calculator_ref::interpreter::Mode;
+
+  let mut words = command.split_whitespace();
+  match (words.next(), words.next()) {
+    (Some("mode"), None) => show.result(interpreter.name()),
+    (Some("mode"), Some(mode)) => match mode.parse::<Mode>() {
+      Ok(mode) => {
+        let limits = interpreter.limits();
+        *interpreter = mode.interpreter();
+        interpreter.set_limits(limits);
+      }
+      Err(e) => show.command_error(e),
+    },
+    (Some("explain"), Some(code)) => explain(*show, code),
+    (Some("trace"), Some("on")) => show.steps = true,
+    (Some("trace"), Some("off")) => show.steps = false,
+    (Some("trace"), _) => show.command_error(tr("repl.trace_usage")),
+    (Some("values"), Some("on")) => show.values = true,
+    (Some("values"), Some("off")) => show.values = false,
+    (Some("values"), _) => show.command_error(tr("repl.values_usage")),
+    (Some("simplify"), Some(_)) => {
+      let input = command.trim_start()["simplify".len()..].trim();
+      simplify(interpreter.as_ref(), *show, input);
+    }
+    (Some("diff"), Some(_)) => {
+      let input = command.trim_start()["diff".len()..].trim();
+      match input.rsplit_once(',') {
+        Some((expr, x)) => diff(interpreter.as_ref(), *show, expr.trim(), x.trim()),
+        None => show.command_error(tr("repl.diff_usage")),
+      }
+    }
+    (Some("solve"), Some(_)) => {
+      let input = command.trim_start()["solve".len()..].trim();
+      solve(interpreter.as_mut(), *show, input);
+    }
+    (Some("solve"), None) => show.command_error(tr("repl.solve_usage")),
+    (Some("lang"), Some(lang)) => match lang.parse() {
+      Ok(locale) => i18n::set_locale(locale),
+      Err(e) => show.command_error(e),
+    },
+    (Some("lang"), None) => show.command_error(tr_with("repl.lang_usage", &[&":lang"])),
+    _ => show.command_error(tr_with("repl.unknown_command", &[&command])),
+  }
+  // both are shown from the steps the interpreter records
+  interpreter.set_trace(show.steps || show.values);
+}
+
+// the expression with its constant parts folded, as the interpreter would evaluate it now
+fn simplify(interpreter: &dyn calculator_ref::interpreter::Evaluator, show: Show, input: &str) {
+  match calculator_ref::ast::Ast::from_str_limited(input, &interpreter.limits()) {
+    Ok(ast) => show.result(interpreter.simplify(&ast)),
+    Err(e) => show.parse_error(e, input),
+  }
+}
+
+// the derivative of the expression by the variable `x`
+fn diff(
+  interpreter: &dyn calculator_ref::interpreter::Evaluator,
+  show: Show,
+  input: &str,
+  x: &str,
+) {
+  let ast = match calculator_ref::ast::Ast::from_str_limited(input, &interpreter.limits()) {
+    Ok(ast) => ast,
+    Err(e) => return show.parse_error(e, input),
+  };
+  match interpreter.diff(&ast, x) {
+    Ok(d) => show.result(d),
+    Err(e) => show.eval_error(e, input, &ast),
+  }
+}
+
+// `:solve lhs = rhs, x, guess` evaluates `solve(lhs = rhs, x, guess)`, with its errors
+// shown in that
+fn solve(interpreter: &mut dyn calculator_ref::interpreter::Evaluator, show: Show, input: &str) {
+  let input = format!("solve({})", input);
+  let ast = match calculator_ref::ast::Ast::from_str_limited(&input, &interpreter.limits()) {
+    Ok(ast) => ast,
+    Err(e) => return show.parse_error(e, &input),
+  };
+  match interpreter.eval_to_string(&ast) {
+    Ok(n) => show.result(n),
+    Err(e) => show.eval_error(e, &input, &ast),
+  }
+}
+
+// the limits the arguments set, and the default ones for the rest
+fn limits(args: &[String]) -> Result<calculator_ref::limits::Limits, String> {
+  let mut limits = calculator_ref::limits::Limits::default();
+  if let Some(depth) = limit(args, "--max-depth")? {
+    limits.depth = depth;
+  }
+  if let Some(steps) = limit(args, "--max-steps")? {
+    limits.steps = steps;
+  }
+  if let Some(bits) = limit(args, "--max-bits")? {
+    limits.bits = bits;
+  }
+  Ok(limits)
+}
+
+// the number after `flag`, if it is given
+fn limit<T: std::str::FromStr>(args: &[String], flag: &str) -> Result<Option<T>, String> {
+  match args.iter().position(|arg| arg == flag) {
+    Some(i) => match args.get(i + 1).map(|n| n.parse()) {
+      Some(Ok(n)) => Ok(Some(n)),
+      _ => Err(tr_with("repl.limit_usage", &[&flag])),
+    },
+    None => Ok(None),
+  }
+}
+
+// the long description of an error code such as `C0201`
+fn explain(show: Show, code: &str) {
+  match calculator_ref::codes::explain(code) {
+    Some(text) => show.result(text),
+    None => show.command_error(tr_with("repl.not_a_code", &[&code])),
+  }
+}
+
 fn main() {
   use std::io::{stdin, BufRead, BufReader};
 
-  let mut interpreter = calculator_ref::interpreter::Interpreter::new();
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  // `--lang ja` picks the language of the messages, which defaults to the environment's
+  i18n::set_locale(i18n::Locale::from_env());
+  if let Some(i) = args.iter().position(|arg| arg == "--lang") {
+    match args.get(i + 1).map(|lang| lang.parse()) {
+      Some(Ok(locale)) => i18n::set_locale(locale),
+      Some(Err(e)) => {
+        eprintln!("{}", e);
+        return;
+      }
+      None => {
+        eprintln!("{}", tr_with("repl.lang_usage", &[&"--lang"]));
+        return;
+      }
+    }
+  }
+
+  if let Some(i) = args.iter().position(|arg| arg == "--explain") {
+    match args.get(i + 1) {
+      Some(code) => explain(Show::default(), code),
+      None => eprintln!("{}", tr("repl.explain_usage")),
+    }
+    return;
+  }
+
+  // `--max-depth 10000` and the like raise or lower the limits on each input
+  let limits = match limits(&args) {
+    Ok(limits) => limits,
+    Err(e) => {
+      eprintln!("{}", e);
+      return;
+    }
+  };
+  let mut interpreter = calculator_ref::interpreter::Mode::Machine.interpreter();
+  interpreter.set_limits(limits);
+  // `--json` prints every result and error as one JSON object per line, for editor tooling
+  let mut show = Show {
+    json: args.iter().any(|arg| arg == "--json"),
+    ..Show::default()
+  };
 
   let stdin = stdin();
   let stdin = stdin.lock();
@@ -38,28 +268,43 @@ fn main() {
 
   loop {
     pub use crate::calculator_ref::ast;
-    use calculator_ref::show_trace;
 
-    prompt("> ").unwrap();
+    if !show.json {
+      prompt(tr("repl.prompt")).unwrap();
+    }
     if let Some(Ok(line)) = lines.next() {
-      let ast = match line.parse::<ast::Ast>() {
+      if let Some(command) = line.trim().strip_prefix(':') {
+        run_command(&mut interpreter, &mut show, command);
+        continue;
+      }
+      let ast = match ast::Ast::from_str_limited(&line, &interpreter.limits()) {
         Ok(ast) => ast,
         Err(e) => {
-          e.show_diagnostic(&line);
-          show_trace(e);
+          show.parse_error(e, &line);
           continue;
         }
       };
-      let n = match interpreter.eval(&ast) {
-        Ok(n) => n,
-        Err(e) => {
-          e.show_diagnostic(&line);
-          show_trace(e);
-          continue;
+      let n = interpreter.eval_to_string(&ast);
+      // the steps to the result, or to the error, as they were asked for
+      let steps = interpreter.trace_to_strings();
+      let (show_values, show_steps) = (show.values && !show.json, show.steps && !show.json);
+      if show_values && !steps.is_empty() {
+        println!("{}", calculator_ref::trace::annotate(&line, &steps));
+      }
+      if show_steps && !steps.is_empty() {
+        let mut texts = calculator_ref::trace::render(&line, &steps);
+        if let Ok(ref n) = n {
+          if texts.last() != Some(n) {
+            texts.push(n.clone());
+          }
         }
-      };
-
-      println!("{:?}", n);
+        println!("{}", texts.join(" → "));
+      }
+      match n {
+        Ok(_) if show_steps && !steps.is_empty() => {}
+        Ok(n) => show.result(n),
+        Err(e) => show.eval_error(e, &line, &ast),
+      }
     } else {
       break;
     }